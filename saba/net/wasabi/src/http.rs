@@ -1,85 +1,189 @@
 extern crate alloc;
-use core::net::SocketAddr;
+use core::net::IpAddr;
 
+use alloc::format;
 use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
 use saba_core::error::Error;
+use saba_core::http::follow_redirects;
+use saba_core::http::Connection;
+use saba_core::http::KeepAlive;
+use saba_core::http::resolve_socket_addr;
+use saba_core::http::retry;
+use saba_core::http::DEFAULT_MAX_BODY_SIZE;
+use saba_core::http::HttpLogger;
+use saba_core::http::HttpRequest;
 use saba_core::http::HttpResponse;
+use saba_core::http::RedirectedResponse;
+use saba_core::http::Resolver;
+use saba_core::renderer::dom::node::Window;
+use saba_core::renderer::page::fetch_document;
+use saba_core::url::Url;
+use noli::net::lookup_host;
 use noli::net::TcpStream;
-use alloc::vec::Vec;
-pub struct HttpClient {}
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+/// noliのTcpStreamを使うConnection
+pub struct NoliConnection(TcpStream);
+
+impl Connection for NoliConnection {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        match self.0.read(buf) {
+            Ok(bytes) => Ok(bytes),
+            Err(_) => Err(Error::Network(
+                "Failed to receive a request from TCP stream".to_string(),
+            )),
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        match self.0.write(buf) {
+            Ok(bytes) => Ok(bytes),
+            Err(_) => Err(Error::Network(
+                "Failed to send a request to TCP stream".to_string(),
+            )),
+        }
+    }
+}
+
+/// noliのlookup_hostで名前解決する、既定のResolver
+pub struct NoliResolver;
+
+impl Resolver for NoliResolver {
+    fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, Error> {
+        match lookup_host(host) {
+            Ok(ips) => Ok(ips.into_iter().map(|ip| ip.into()).collect()),
+            Err(e) => Err(Error::Network(format!(
+                "Failed to find IP addresses: {:#?}",
+                e
+            ))),
+        }
+    }
+}
+
+pub struct HttpClient {
+    /// 設定されている場合、Accept-Languageヘッダとしてそのまま送信する(例: "ja, en;q=0.8")
+    accept_language: Option<String>,
+    /// レスポンスのボディのバイト数の上限。超えると読み込みをやめてエラーを返す
+    max_body_size: usize,
+    /// 設定されている場合、送受信したリクエストとレスポンスを渡す
+    logger: Option<HttpLogger>,
+    /// GETリクエストが通信の失敗や5xxのレスポンスで終わった場合に、やり直す回数
+    retries: usize,
+    /// ホスト名の名前解決に使う。既定ではNoliResolver
+    resolver: Rc<dyn Resolver>,
+    /// trueの場合、Connection: keep-aliveを送り、サーバが許せば次のリクエストで接続を使い回す
+    keep_alive: bool,
+    /// 使い回すために保持している接続
+    connections: RefCell<KeepAlive<NoliConnection>>,
+}
 
 impl HttpClient {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            accept_language: None,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            logger: None,
+            retries: 0,
+            resolver: Rc::new(NoliResolver),
+            keep_alive: false,
+            connections: RefCell::new(KeepAlive::new()),
+        }
     }
 
-    pub fn get(&self, host: String, port: u16, path: String) -> Result<HttpResponse, Error> {
-        let ips = match lookup_host(&host) {
-            Ok(ips) => ips,
-            Err(e) => {
-                return  Err(Error::Network(format!(
-                    "Failed to find IP addresses: {:#?}",
-                    e
-                )))
-            }
-        };
+    pub fn set_max_body_size(&mut self, max_body_size: usize) {
+        self.max_body_size = max_body_size;
+    }
 
-        if ips.len() < 1 {
-            return  Err(Error::Network("Failed to find IP addresses".to_string()));
-        }
+    pub fn set_accept_language(&mut self, accept_language: String) {
+        self.accept_language = Some(accept_language);
+    }
 
-        let socket_addr: SocketAddr = (ips[0], port).into();
+    pub fn set_logger(&mut self, logger: HttpLogger) {
+        self.logger = Some(logger);
+    }
 
-        let mut stream = match TcpStream::connect(socket_addr) {
-            Ok(stream) => stream,
-            Err(_) => {
-                return  Err(Error::Network(
-                    "Failed to connect to TCP stream".to_string(),
-                ))
-            }
-        };
+    pub fn set_retries(&mut self, retries: usize) {
+        self.retries = retries;
+    }
 
-        let mut request = String::from("GET /");
-        request.push_str(&path);
-        request.push_str(" HTTP/1.1\n");
-
-        // ヘッダの追加
-        request.push_str("Host: ");
-        request.push_str(&host);
-        request.push('\n');
-        request.push_str("Accept: text/html\n");
-        request.push_str("Connection: close\n");
-        request.push('\n');
-
-        let bytes_written = match stream.write(request.as_bytes()) {
-            Ok(bytes) => bytes,
-            Err(_) => {
-                return  Err(Error::Network(
-                    "Failed to send a request to TCP stream".to_string(),
-                ));
-            }
-        };
+    pub fn set_resolver(&mut self, resolver: Rc<dyn Resolver>) {
+        self.resolver = resolver;
+    }
 
-        let mut received = Vec::new();
-        loop {
-            let mut buf = [0u8; 4096];
-            let bytes_read = match stream.read(&mut buf) {
-                Ok(bytes) => bytes,
-                Err(_) => {
-                    return  Err(Error::Network(
-                        "Failed to receive a request from TCP stream".to_string(),
-                    ))
-                }
-            };
-            if bytes_read == 0 {
-                break;
-            }
-            received.extend_from_slice(&buf[..bytes_read]);
+    pub fn set_keep_alive(&mut self, keep_alive: bool) {
+        self.keep_alive = keep_alive;
+    }
+
+    pub fn get(&self, host: String, port: u16, path: String) -> Result<HttpResponse, Error> {
+        retry(self.retries, || {
+            self.send(port, HttpRequest::new(host.clone(), path.clone()))
+        })
+    }
+
+    /// フォームの送信などで作った任意のリクエストを`port`に送る。keep_aliveの場合は、
+    /// 同じホストとポートへの接続が残っていればそれを使う
+    pub fn send(&self, port: u16, mut request: HttpRequest) -> Result<HttpResponse, Error> {
+        if let Some(ref accept_language) = self.accept_language {
+            request.add_header("Accept-Language".to_string(), accept_language.clone());
         }
+        request.set_keep_alive(self.keep_alive);
+
+        if let Some(ref logger) = self.logger {
+            logger.log_request(&request);
+        }
+
+        let response = self.connections.borrow_mut().send(
+            port,
+            &request,
+            self.max_body_size,
+            || {
+                let socket_addr =
+                    resolve_socket_addr(self.resolver.as_ref(), &request.host(), port)?;
+                match TcpStream::connect(socket_addr) {
+                    Ok(stream) => Ok(NoliConnection(stream)),
+                    Err(_) => Err(Error::Network(
+                        "Failed to connect to TCP stream".to_string(),
+                    )),
+                }
+            },
+        )?;
 
-        match core::str::from_utf8(&received) {
-            Ok(response) => HttpResponse::new(response.to_string()),
-            Err(e) => Err(Error::Network(format!("Invalid receiced response: {}", e))),
+        if let Some(ref logger) = self.logger {
+            logger.log_response(&response);
         }
+
+        Ok(response)
+    }
+
+    /// `url`にGETリクエストを送り、リダイレクトを辿った最終的なレスポンスとURLを返す
+    pub fn get_with_redirects(&self, url: &Url) -> Result<RedirectedResponse, Error> {
+        follow_redirects(url.clone(), |u| self.get_url(u))
+    }
+
+    /// `url`の文書を取得し(リダイレクトも辿る)、HTMLとして解析したWindowを返す
+    pub fn fetch_document(&self, url: &Url) -> Result<Rc<RefCell<Window>>, Error> {
+        fetch_document(url, |u| self.get_url(u))
+    }
+
+    fn get_url(&self, url: &Url) -> Result<HttpResponse, Error> {
+        let port = match url.port().parse::<u16>() {
+            Ok(port) => port,
+            Err(e) => {
+                return Err(Error::UnexpectedInput(format!(
+                    "invalid port number {}: {}",
+                    url.port(),
+                    e
+                )))
+            }
+        };
+        let path = if url.searchpart().is_empty() {
+            url.path()
+        } else {
+            format!("{}?{}", url.path(), url.searchpart())
+        };
+        self.get(url.host(), port, path)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,279 @@
+use crate::renderer::dom::node::Window;
+use crate::url::Url;
+use alloc::rc::Rc;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// https://html.spec.whatwg.org/multipage/nav-history-apis.html#the-history-interface
+/// タブで訪れたURLの履歴。戻ったあとに新しいURLを訪れると、進む方向の履歴は捨てる
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: Vec<Url>,
+    /// 現在のエントリの位置。履歴が空の場合は使わない
+    index: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            index: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn current(&self) -> Option<Url> {
+        self.entries.get(self.index).cloned()
+    }
+
+    pub fn push(&mut self, url: Url) {
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.index + 1);
+        }
+        self.entries.push(url);
+        self.index = self.entries.len() - 1;
+    }
+
+    /// 1つ前のエントリに戻り、そのURLを返す。戻れない場合はNoneを返す
+    pub fn back(&mut self) -> Option<Url> {
+        if self.index == 0 {
+            return None;
+        }
+        self.index -= 1;
+        self.current()
+    }
+
+    /// 1つ先のエントリに進み、そのURLを返す。進めない場合はNoneを返す
+    pub fn forward(&mut self) -> Option<Url> {
+        if self.index + 1 >= self.entries.len() {
+            return None;
+        }
+        self.index += 1;
+        self.current()
+    }
+}
+
+/// 1つの文書を表示するタブ。タブごとにWindowと履歴を持つ
+#[derive(Debug, Clone)]
+pub struct Tab {
+    window: Rc<RefCell<Window>>,
+    history: History,
+    /// 表示している文書のURL
+    url: Url,
+}
+
+impl Tab {
+    /// about:blankの空の文書を表示するタブを作る
+    pub fn new() -> Self {
+        Self {
+            window: Rc::new(RefCell::new(Window::new())),
+            history: History::new(),
+            url: Url::new("about:blank".to_string())
+                .parse()
+                .expect("failed to parse about:blank"),
+        }
+    }
+
+    pub fn window(&self) -> Rc<RefCell<Window>> {
+        self.window.clone()
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn history_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    /// 読み込んだ文書を表示し、そのURLを履歴に加える
+    pub fn navigate(&mut self, url: Url, window: Rc<RefCell<Window>>) {
+        self.history.push(url.clone());
+        self.url = url;
+        self.window = window;
+    }
+}
+
+impl Default for Tab {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// タブの一覧を管理する。タブは常に1つ以上あり、そのうち1つが選択されている
+#[derive(Debug, Clone)]
+pub struct Browser {
+    tabs: Vec<Tab>,
+    active_tab: usize,
+}
+
+impl Browser {
+    /// 空白のタブを1つ開いた状態で作る
+    pub fn new() -> Self {
+        Self {
+            tabs: vec![Tab::new()],
+            active_tab: 0,
+        }
+    }
+
+    pub fn tabs(&self) -> &[Tab] {
+        &self.tabs
+    }
+
+    pub fn active_tab_index(&self) -> usize {
+        self.active_tab
+    }
+
+    pub fn active_tab(&self) -> &Tab {
+        &self.tabs[self.active_tab]
+    }
+
+    pub fn active_tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active_tab]
+    }
+
+    /// 新しい空白のタブを末尾に開いて選択し、その位置を返す
+    pub fn add_tab(&mut self) -> usize {
+        self.tabs.push(Tab::new());
+        self.active_tab = self.tabs.len() - 1;
+        self.active_tab
+    }
+
+    /// 存在しないタブを指定した場合は何もせずにfalseを返す
+    pub fn select_tab(&mut self, index: usize) -> bool {
+        if index >= self.tabs.len() {
+            return false;
+        }
+        self.active_tab = index;
+        true
+    }
+
+    /// `index`のタブを閉じる。最後のタブを閉じた場合は、代わりに空白のタブを開く。
+    /// 選択されているタブを閉じた場合は、その次(末尾の場合は前)のタブを選択する
+    pub fn close_tab(&mut self, index: usize) -> bool {
+        if index >= self.tabs.len() {
+            return false;
+        }
+
+        self.tabs.remove(index);
+        if self.tabs.is_empty() {
+            self.tabs.push(Tab::new());
+        }
+
+        if index < self.active_tab || self.active_tab >= self.tabs.len() {
+            self.active_tab -= 1;
+        }
+        true
+    }
+}
+
+impl Default for Browser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+
+    fn url(s: &str) -> Url {
+        Url::new(s.to_string()).parse().expect("failed to parse url")
+    }
+
+    fn assert_invariant(browser: &Browser) {
+        assert!(!browser.tabs().is_empty());
+        assert!(browser.active_tab_index() < browser.tabs().len());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = History::new();
+        assert_eq!(None, history.current());
+        assert_eq!(None, history.back());
+
+        history.push(url("http://example.com/a"));
+        history.push(url("http://example.com/b"));
+        history.push(url("http://example.com/c"));
+        assert_eq!("b", history.back().expect("failed to go back").path());
+        assert_eq!("a", history.back().expect("failed to go back").path());
+        assert_eq!(None, history.back());
+        assert_eq!("b", history.forward().expect("failed to go forward").path());
+
+        // 戻った状態で新しいURLを訪れると、進む方向の履歴は捨てられる
+        history.push(url("http://example.com/d"));
+        assert_eq!(3, history.len());
+        assert_eq!(None, history.forward());
+        assert_eq!("b", history.back().expect("failed to go back").path());
+    }
+
+    #[test]
+    fn test_add_and_select_tabs() {
+        let mut browser = Browser::new();
+        assert_eq!(1, browser.tabs().len());
+        assert!(browser.active_tab().url().is_about_blank());
+
+        browser
+            .active_tab_mut()
+            .navigate(url("http://example.com/first"), Rc::new(RefCell::new(Window::new())));
+        assert_eq!(1, browser.add_tab());
+        assert_eq!(1, browser.active_tab_index());
+        assert!(browser.active_tab().url().is_about_blank());
+
+        assert!(browser.select_tab(0));
+        assert_eq!("first", browser.active_tab().url().path());
+        assert_eq!(1, browser.active_tab().history().len());
+        assert!(!browser.select_tab(2));
+        assert_eq!(0, browser.active_tab_index());
+        assert_invariant(&browser);
+    }
+
+    #[test]
+    fn test_close_tabs() {
+        let mut browser = Browser::new();
+        browser.add_tab();
+        browser.add_tab();
+        browser.add_tab();
+        assert_eq!(4, browser.tabs().len());
+
+        // 選択されているタブより前のタブを閉じても、同じタブが選択されたまま
+        browser.select_tab(2);
+        let active_window = browser.active_tab().window();
+        assert!(browser.close_tab(0));
+        assert_eq!(1, browser.active_tab_index());
+        assert!(Rc::ptr_eq(&active_window, &browser.active_tab().window()));
+        assert_invariant(&browser);
+
+        // 末尾の選択されているタブを閉じると、前のタブが選択される
+        browser.select_tab(2);
+        assert!(browser.close_tab(2));
+        assert_eq!(1, browser.active_tab_index());
+        assert_invariant(&browser);
+
+        assert!(!browser.close_tab(5));
+        assert!(browser.close_tab(0));
+        assert!(browser.close_tab(0));
+        assert_invariant(&browser);
+
+        // 最後のタブを閉じると、空白のタブが開かれる
+        assert_eq!(1, browser.tabs().len());
+        assert!(browser.close_tab(0));
+        assert_eq!(1, browser.tabs().len());
+        assert!(browser.active_tab().url().is_about_blank());
+        assert_invariant(&browser);
+    }
+}
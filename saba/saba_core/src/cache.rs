@@ -0,0 +1,150 @@
+use crate::error::Error;
+use crate::http::HttpResponse;
+use crate::url::Url;
+use alloc::vec::Vec;
+
+/// URLをキーとしてHttpResponseを保持するメモリ上のキャッシュ。
+/// 保持できるエントリ数を超えると、最も長い間使われていないエントリから削除する(LRU)。
+/// Cache-Controlヘッダは解釈しないため、古いレスポンスを捨てたい場合は`clear`を呼ぶ
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    max_entries: usize,
+    /// 最近使われたエントリほど後ろに並ぶ
+    entries: Vec<(Url, HttpResponse)>,
+}
+
+impl ResponseCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&mut self, url: &Url) -> Option<HttpResponse> {
+        let index = self.entries.iter().position(|(u, _)| u == url)?;
+        let entry = self.entries.remove(index);
+        let response = entry.1.clone();
+        self.entries.push(entry);
+        Some(response)
+    }
+
+    pub fn insert(&mut self, url: Url, response: HttpResponse) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        if let Some(index) = self.entries.iter().position(|(u, _)| *u == url) {
+            self.entries.remove(index);
+        }
+
+        if self.entries.len() >= self.max_entries {
+            self.entries.remove(0);
+        }
+
+        self.entries.push((url, response));
+    }
+
+    /// キャッシュにあればそれを返し、なければ`fetch`でレスポンスを取得してキャッシュに保存する
+    pub fn get_or_fetch<F>(&mut self, url: &Url, fetch: F) -> Result<HttpResponse, Error>
+    where
+        F: FnOnce() -> Result<HttpResponse, Error>,
+    {
+        if let Some(response) = self.get(url) {
+            return Ok(response);
+        }
+
+        let response = fetch()?;
+        self.insert(url.clone(), response.clone());
+        Ok(response)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use alloc::format;
+
+    fn url(s: &str) -> Url {
+        Url::new(s.to_string()).parse().expect("failed to parse url")
+    }
+
+    fn fetch(count: &mut usize, body: &str) -> Result<HttpResponse, Error> {
+        *count += 1;
+        HttpResponse::new(format!("HTTP/1.1 200 OK\nDate: xx xx xx\n\n{}", body))
+    }
+
+    #[test]
+    fn test_second_fetch_hits_cache() {
+        let mut cache = ResponseCache::new(2);
+        let mut count = 0;
+
+        let first = cache
+            .get_or_fetch(&url("http://example.com/a"), || fetch(&mut count, "a"))
+            .expect("failed to fetch");
+        let second = cache
+            .get_or_fetch(&url("http://example.com/a"), || fetch(&mut count, "a"))
+            .expect("failed to fetch");
+
+        assert_eq!(1, count);
+        assert_eq!("a", first.body());
+        assert_eq!("a", second.body());
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let mut cache = ResponseCache::new(2);
+        let mut count = 0;
+
+        for path in ["a", "b", "c"] {
+            cache
+                .get_or_fetch(&url(&format!("http://example.com/{}", path)), || {
+                    fetch(&mut count, path)
+                })
+                .expect("failed to fetch");
+        }
+        assert_eq!(3, count);
+        assert_eq!(2, cache.len());
+
+        assert!(cache.get(&url("http://example.com/a")).is_none());
+        assert!(cache.get(&url("http://example.com/b")).is_some());
+        assert!(cache.get(&url("http://example.com/c")).is_some());
+    }
+
+    #[test]
+    fn test_get_refreshes_entry() {
+        let mut cache = ResponseCache::new(2);
+        let mut count = 0;
+        cache.insert(url("http://example.com/a"), fetch(&mut count, "a").unwrap());
+        cache.insert(url("http://example.com/b"), fetch(&mut count, "b").unwrap());
+
+        // aを使ったので、次に追加したときはbが削除される
+        assert!(cache.get(&url("http://example.com/a")).is_some());
+        cache.insert(url("http://example.com/c"), fetch(&mut count, "c").unwrap());
+
+        assert!(cache.get(&url("http://example.com/a")).is_some());
+        assert!(cache.get(&url("http://example.com/b")).is_none());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut cache = ResponseCache::new(2);
+        let mut count = 0;
+        cache.insert(url("http://example.com/a"), fetch(&mut count, "a").unwrap());
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
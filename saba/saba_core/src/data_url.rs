@@ -0,0 +1,192 @@
+use crate::error::Error;
+use crate::url::Url;
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// https://fetch.spec.whatwg.org/#data-urls
+/// data:のURLに埋め込まれたデータ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataUrl {
+    /// パラメータを含むMIMEタイプ(例: "text/html;charset=utf-8")
+    mime_type: String,
+    body: Vec<u8>,
+}
+
+impl DataUrl {
+    /// https://fetch.spec.whatwg.org/#data-url-processor
+    pub fn parse(url: &Url) -> Result<Self, Error> {
+        if url.scheme() != "data" {
+            return Err(Error::UnexpectedInput(format!(
+                "not a data: URL: {}:{}",
+                url.scheme(),
+                url.path()
+            )));
+        }
+
+        let input = url.path();
+        let (mime_type, body) = match input.split_once(',') {
+            Some((m, b)) => (m.trim(), b),
+            None => {
+                return Err(Error::UnexpectedInput(
+                    "data: URL has no ',' separating the data".to_string(),
+                ))
+            }
+        };
+
+        let body = percent_decode(body.as_bytes());
+
+        // MIMEタイプが";base64"で終わる場合は、データをBase64として復号する
+        let (mime_type, body) = match strip_base64_suffix(mime_type) {
+            Some(m) => (m, forgiving_base64_decode(&body)?),
+            None => (mime_type, body),
+        };
+
+        let mime_type = if mime_type.is_empty() {
+            "text/plain;charset=US-ASCII".to_string()
+        } else if mime_type.starts_with(';') {
+            format!("text/plain{}", mime_type)
+        } else {
+            mime_type.to_string()
+        };
+
+        Ok(Self { mime_type, body })
+    }
+
+    pub fn mime_type(&self) -> String {
+        self.mime_type.clone()
+    }
+
+    pub fn body(&self) -> Vec<u8> {
+        self.body.clone()
+    }
+}
+
+/// ";base64"(前後の空白と大文字小文字は区別しない)を取り除いたMIMEタイプを返す
+fn strip_base64_suffix(mime_type: &str) -> Option<&str> {
+    let (rest, last) = mime_type.rsplit_once(';')?;
+    if last.trim().eq_ignore_ascii_case("base64") {
+        Some(rest.trim_end())
+    } else {
+        None
+    }
+}
+
+/// https://url.spec.whatwg.org/#percent-decode
+fn percent_decode(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == b'%' && i + 2 < input.len() {
+            if let (Some(h), Some(l)) = (hex_value(input[i + 1]), hex_value(input[i + 2])) {
+                output.push(h * 16 + l);
+                i += 3;
+                continue;
+            }
+        }
+        output.push(input[i]);
+        i += 1;
+    }
+    output
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// https://infra.spec.whatwg.org/#forgiving-base64-decode
+fn forgiving_base64_decode(input: &[u8]) -> Result<Vec<u8>, Error> {
+    let invalid = || Error::UnexpectedInput("invalid base64 data in data: URL".to_string());
+
+    let mut data: Vec<u8> = input
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+
+    match data.len() % 4 {
+        // 末尾の1つか2つの`=`による埋め草を取り除く
+        0 => {
+            if data.ends_with(b"==") {
+                data.truncate(data.len() - 2);
+            } else if data.ends_with(b"=") {
+                data.truncate(data.len() - 1);
+            }
+        }
+        // 4で割った余りが1になる長さのデータは、完全なバイトを表せない
+        1 => return Err(invalid()),
+        _ => {}
+    }
+
+    let mut output = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for b in data {
+        let value = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(invalid()),
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn data_url(s: &str) -> Result<DataUrl, Error> {
+        let url = Url::new(s.to_string()).parse().expect("failed to parse url");
+        DataUrl::parse(&url)
+    }
+
+    #[test]
+    fn test_plain_data_url() {
+        let data = data_url("data:text/html,<p>a%20b?c</p>").expect("failed to parse data url");
+        assert_eq!("text/html", data.mime_type());
+        assert_eq!(b"<p>a b?c</p>".to_vec(), data.body());
+    }
+
+    #[test]
+    fn test_base64_data_url() {
+        // "<p>hello</p>"をBase64で符号化したもの
+        let data = data_url("data:text/html;charset=utf-8;base64,PHA+aGVsbG88L3A+")
+            .expect("failed to parse data url");
+        assert_eq!("text/html;charset=utf-8", data.mime_type());
+        assert_eq!(b"<p>hello</p>".to_vec(), data.body());
+
+        let data = data_url("data:;BASE64,YQ==").expect("failed to parse data url");
+        assert_eq!("text/plain;charset=US-ASCII", data.mime_type());
+        assert_eq!(vec![b'a'], data.body());
+    }
+
+    #[test]
+    fn test_default_mime_type() {
+        let data = data_url("data:,x").expect("failed to parse data url");
+        assert_eq!("text/plain;charset=US-ASCII", data.mime_type());
+    }
+
+    #[test]
+    fn test_malformed_data_url() {
+        assert!(data_url("data:text/html").is_err());
+        assert!(data_url("data:;base64,a").is_err());
+        assert!(data_url("data:;base64,a*b=").is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use alloc::string::String;
+
+/// https://encoding.spec.whatwg.org/#names-and-labels
+/// 本書ではUTF-8とISO-8859-1(Latin-1)のみをサポートする
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Iso8859_1,
+}
+
+impl Encoding {
+    /// https://encoding.spec.whatwg.org/#concept-encoding-get
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" | "unicode-1-1-utf-8" => Some(Encoding::Utf8),
+            "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "l1" | "us-ascii" | "ascii" => {
+                Some(Encoding::Iso8859_1)
+            }
+            _ => None,
+        }
+    }
+
+    /// Content-Typeヘッダの値(例: "text/html; charset=ISO-8859-1")から文字コードを決める。
+    /// charsetの指定がない場合やサポートしていない文字コードの場合はUTF-8とみなす
+    pub fn from_content_type(content_type: &str) -> Self {
+        for parameter in content_type.split(';').skip(1) {
+            if let Some((name, value)) = parameter.split_once('=') {
+                if name.trim().eq_ignore_ascii_case("charset") {
+                    let value = value.trim().trim_matches('"');
+                    return Encoding::from_label(value).unwrap_or(Encoding::Utf8);
+                }
+            }
+        }
+
+        Encoding::Utf8
+    }
+}
+
+/// バイト列を文字列に変換する。UTF-8として不正なバイト列は置換文字(U+FFFD)になる
+pub fn decode(bytes: &[u8], encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+        // ISO-8859-1の各バイトは、同じ値のUnicodeのコードポイントに対応する
+        Encoding::Iso8859_1 => bytes.iter().map(|b| *b as char).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding_from_content_type() {
+        assert_eq!(Encoding::Utf8, Encoding::from_content_type("text/html"));
+        assert_eq!(
+            Encoding::Utf8,
+            Encoding::from_content_type("text/html; charset=utf-8")
+        );
+        assert_eq!(
+            Encoding::Iso8859_1,
+            Encoding::from_content_type("text/html; charset=\"ISO-8859-1\"")
+        );
+        assert_eq!(
+            Encoding::Utf8,
+            Encoding::from_content_type("text/html; charset=shift_jis")
+        );
+    }
+
+    #[test]
+    fn test_decode_latin1() {
+        let bytes = [0x63, 0x61, 0x66, 0xE9, 0x20, 0xA9];
+        assert_eq!("café ©", decode(&bytes, Encoding::Iso8859_1));
+    }
+
+    #[test]
+    fn test_decode_utf8() {
+        let bytes = "日本語 café".as_bytes();
+        assert_eq!("日本語 café", decode(bytes, Encoding::Utf8));
+    }
+
+    #[test]
+    fn test_decode_invalid_utf8() {
+        let bytes = [0x61, 0xFF, 0x62];
+        assert_eq!("a\u{FFFD}b", decode(&bytes, Encoding::Utf8));
+    }
+}
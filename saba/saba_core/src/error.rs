@@ -3,7 +3,11 @@ use alloc::string::String;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     Network(String),
+    /// 要求されたファイルやリソースが存在しない
+    NotFound(String),
     UnexpectedInput(String),
+    /// strictモードでのHTMLのパースエラー
+    InvalidHtml(String),
     InvalidUI(String),
     Other(String),
 }
\ No newline at end of file
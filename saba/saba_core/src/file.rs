@@ -0,0 +1,130 @@
+use crate::error::Error;
+use crate::http::HttpResponse;
+use crate::url::Url;
+use alloc::format;
+use alloc::vec::Vec;
+
+/// file:のURLの内容を読み込む手段。プラットフォームのファイルシステムや、テスト用の
+/// メモリ上のファイルを実装として渡す
+pub trait FileSource {
+    /// `path`は先頭の`/`を含まないパス(Url::path()の値)。ファイルが存在しなければNoneを返す
+    fn read(&self, path: &str) -> Option<Vec<u8>>;
+}
+
+/// file:のURLのファイルを読み込み、HTTPで200 OKを受け取ったかのようなHttpResponseを返す。
+/// Content-Typeは拡張子から決める
+pub fn fetch_file(url: &Url, source: &dyn FileSource) -> Result<HttpResponse, Error> {
+    if url.scheme() != "file" {
+        return Err(Error::UnexpectedInput(format!(
+            "not a file: URL: {}",
+            url.path()
+        )));
+    }
+
+    let path = url.path();
+    let body = match source.read(&path) {
+        Some(body) => body,
+        None => return Err(Error::NotFound(format!("file not found: /{}", path))),
+    };
+
+    let mut raw = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+        mime_type_from_path(&path),
+        body.len()
+    )
+    .into_bytes();
+    raw.extend_from_slice(&body);
+    HttpResponse::from_bytes(&raw)
+}
+
+fn mime_type_from_path(path: &str) -> &'static str {
+    let extension = match path.rsplit_once('.') {
+        Some((_, e)) => e,
+        None => "",
+    };
+    if extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm") {
+        "text/html"
+    } else if extension.eq_ignore_ascii_case("css") {
+        "text/css"
+    } else if extension.eq_ignore_ascii_case("txt") {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use crate::renderer::dom::node::ElementKind;
+    use crate::renderer::page::fetch_document;
+    use alloc::string::String;
+    use alloc::vec;
+
+    struct MockFileSource {
+        files: Vec<(String, Vec<u8>)>,
+    }
+
+    impl FileSource for MockFileSource {
+        fn read(&self, path: &str) -> Option<Vec<u8>> {
+            self.files
+                .iter()
+                .find(|(p, _)| p == path)
+                .map(|(_, body)| body.clone())
+        }
+    }
+
+    fn source() -> MockFileSource {
+        MockFileSource {
+            files: vec![(
+                "home/index.html".to_string(),
+                b"<html><head></head><body><p>local</p></body></html>".to_vec(),
+            )],
+        }
+    }
+
+    fn url(s: &str) -> Url {
+        Url::new(s.to_string()).parse().expect("failed to parse url")
+    }
+
+    #[test]
+    fn test_fetch_file() {
+        let response =
+            fetch_file(&url("file:///home/index.html"), &source()).expect("failed to fetch a file");
+        assert_eq!(200, response.status_code());
+        assert_eq!(Ok("text/html".to_string()), response.header_value("Content-Type"));
+        assert_eq!(
+            "<html><head></head><body><p>local</p></body></html>",
+            response.body()
+        );
+    }
+
+    #[test]
+    fn test_fetch_missing_file() {
+        assert_eq!(
+            Some(Error::NotFound("file not found: /home/missing.html".to_string())),
+            fetch_file(&url("file:///home/missing.html"), &source()).err()
+        );
+    }
+
+    #[test]
+    fn test_fetch_document_from_file() {
+        let source = source();
+        let window = fetch_document(&url("file:///home/index.html"), |u| fetch_file(u, &source))
+            .expect("failed to fetch a document");
+        let html = window
+            .borrow()
+            .document()
+            .borrow()
+            .first_child()
+            .expect("failed to get html");
+        let body = html.borrow().last_child().upgrade().expect("failed to get body");
+        let p = body.borrow().first_child().expect("failed to get p");
+        assert_eq!(Some(ElementKind::P), p.borrow().element_kind());
+        assert_eq!(
+            "home/index.html",
+            window.borrow().base_url().expect("failed to get base url").path()
+        );
+    }
+}
@@ -1,8 +1,15 @@
 use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::format;
+use alloc::rc::Rc;
+use core::fmt;
+use core::net::IpAddr;
+use core::net::SocketAddr;
+use crate::encoding::decode;
+use crate::encoding::Encoding;
 use crate::error::Error;
 use crate::alloc::string::ToString;
+use crate::url::Url;
 
 #[derive(Debug, Clone)]
 pub struct Header {
@@ -14,6 +21,168 @@ impl  Header {
     pub fn new(name: String, value: String) -> Self {
         Self { name, value }
     }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn value(&self) -> String {
+        self.value.clone()
+    }
+}
+
+/// https://httpwg.org/specs/rfc9110.html#methods
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+        }
+    }
+}
+
+/// https://httpwg.org/specs/rfc9112.html#http.version
+/// リクエスト行に書くHTTPのバージョン
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Version {
+    /// HTTP/1.1を正しく扱えないサーバのためのモード。Hostヘッダを付けない
+    Http10,
+    Http11,
+}
+
+impl Version {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Version::Http10 => "HTTP/1.0",
+            Version::Http11 => "HTTP/1.1",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    method: Method,
+    version: Version,
+    host: String,
+    path: String,
+    headers: Vec<Header>,
+    body: Option<String>,
+    /// trueの場合、Connection: keep-aliveを送り、レスポンスの後も接続を使い続けるよう求める
+    keep_alive: bool,
+}
+
+impl HttpRequest {
+    /// `path`は先頭の`/`を含まないパス(Url::path()の値)。クエリを含めてもよい
+    pub fn new(host: String, path: String) -> Self {
+        Self {
+            method: Method::Get,
+            version: Version::Http11,
+            host,
+            path,
+            headers: Vec::new(),
+            body: None,
+            keep_alive: false,
+        }
+    }
+
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    pub fn set_method(&mut self, method: Method) {
+        self.method = method;
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: Version) {
+        self.version = version;
+    }
+
+    pub fn host(&self) -> String {
+        self.host.clone()
+    }
+
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    pub fn headers(&self) -> Vec<Header> {
+        self.headers.clone()
+    }
+
+    pub fn body(&self) -> Option<String> {
+        self.body.clone()
+    }
+
+    /// ボディを設定すると、送信時にContent-Lengthヘッダが付く
+    pub fn set_body(&mut self, body: String) {
+        self.body = Some(body);
+    }
+
+    pub fn add_header(&mut self, name: String, value: String) {
+        self.headers.push(Header::new(name, value));
+    }
+
+    pub fn keep_alive(&self) -> bool {
+        self.keep_alive
+    }
+
+    pub fn set_keep_alive(&mut self, keep_alive: bool) {
+        self.keep_alive = keep_alive;
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut request = self.serialize_head();
+        if let Some(ref body) = self.body {
+            request.push_str(body);
+        }
+
+        request
+    }
+
+    /// リクエスト行とヘッダ(末尾の空行まで)を文字列にする
+    fn serialize_head(&self) -> String {
+        let mut request = String::from(self.method.as_str());
+        request.push_str(" /");
+        request.push_str(&self.path);
+        request.push(' ');
+        request.push_str(self.version.as_str());
+        request.push('\n');
+
+        // ヘッダの追加。HostヘッダはHTTP/1.1でのみ必須
+        if self.version == Version::Http11 {
+            request.push_str("Host: ");
+            request.push_str(&self.host);
+            request.push('\n');
+        }
+        request.push_str("Accept: text/html\n");
+        for h in &self.headers {
+            request.push_str(&h.name);
+            request.push_str(": ");
+            request.push_str(&h.value);
+            request.push('\n');
+        }
+        if let Some(ref body) = self.body {
+            request.push_str(&format!("Content-Length: {}\n", body.len()));
+        }
+        if self.keep_alive {
+            request.push_str("Connection: keep-alive\n");
+        } else {
+            request.push_str("Connection: close\n");
+        }
+        request.push('\n');
+
+        request
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -22,7 +191,10 @@ pub struct HttpResponse {
     status_code: u32,
     reason: String,
     headers: Vec<Header>,
+    /// Content-Typeのcharsetに従って文字列に変換したボディ
     body: String,
+    /// 受信したままのボディのバイト列
+    body_bytes: Vec<u8>,
 }
 
 impl HttpResponse {
@@ -62,9 +234,35 @@ impl HttpResponse {
             reason: statuses[2].to_string(),
             headers,
             body: body.to_string(),
+            body_bytes: body.as_bytes().to_vec(),
         })
     }
 
+    /// ネットワークから受け取ったバイト列からHttpResponseを作る。
+    /// ボディはContent-Typeヘッダのcharsetに従って文字列に変換する(指定がなければUTF-8)
+    pub fn from_bytes(raw_response: &[u8]) -> Result<Self, Error> {
+        let (head, body) = match find_end_of_headers(raw_response) {
+            Some((end_of_head, start_of_body)) => {
+                (&raw_response[..end_of_head], &raw_response[start_of_body..])
+            }
+            None => return Self::new(decode(raw_response, Encoding::Utf8)),
+        };
+
+        // ステータス行とヘッダはASCIIの範囲で書かれているため、UTF-8として扱う
+        let mut head = decode(head, Encoding::Utf8);
+        head.push_str("\n\n");
+        let mut response = Self::new(head)?;
+
+        let encoding = match response.header_value("Content-Type") {
+            Ok(content_type) => Encoding::from_content_type(&content_type),
+            Err(_) => Encoding::Utf8,
+        };
+        response.body = decode(body, encoding);
+        response.body_bytes = body.to_vec();
+
+        Ok(response)
+    }
+
     pub fn version(&self) -> String {
         self.version.clone()
     }
@@ -85,6 +283,24 @@ impl HttpResponse {
         self.body.clone()
     }
 
+    /// 画像などのバイナリのボディを扱うための、受信したままのバイト列
+    pub fn body_bytes(&self) -> Vec<u8> {
+        self.body_bytes.clone()
+    }
+
+    /// ボディのバイト列をUTF-8として文字列に変換する。不正なバイト列はU+FFFDに置き換える
+    pub fn body_as_utf8_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.body_bytes).into_owned()
+    }
+
+    /// Content-Typeヘッダの値を返す。ヘッダがない場合は、ボディの先頭からMIMEタイプを推測する
+    pub fn content_type(&self) -> String {
+        match self.header_value("Content-Type") {
+            Ok(content_type) => content_type,
+            Err(_) => String::from(sniff_mime_type(&self.body_bytes)),
+        }
+    }
+
     pub fn header_value(&self, name: &str) -> Result<String, String> {
         for h in &self.headers {
             if h.name == name {
@@ -94,11 +310,434 @@ impl HttpResponse {
 
         Err(format!("failed to find {} in headers", name))
     }
+
+    /// https://httpwg.org/specs/rfc9112.html#message.body.length
+    /// ヘッダからボディのバイト数が決まる場合はそのバイト数を返す。1xx・204・304のレスポンスは
+    /// ボディを持たず、それ以外はContent-Lengthに従う。決まらない場合は、接続が閉じられる
+    /// までがボディになるのでNoneを返す
+    pub fn body_length(&self) -> Option<usize> {
+        if (100..200).contains(&self.status_code) || matches!(self.status_code, 204 | 304) {
+            return Some(0);
+        }
+        self.headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case("Content-Length"))
+            .and_then(|h| h.value.parse().ok())
+    }
+
+    /// https://httpwg.org/specs/rfc9112.html#persistent.connections
+    /// レスポンスの後も接続を使い続けてよい場合はtrue。Connectionヘッダに"close"があれば
+    /// false、HTTP/1.1では既定でtrue、HTTP/1.0では"keep-alive"がある場合のみtrue
+    pub fn is_persistent(&self) -> bool {
+        let options = self
+            .headers
+            .iter()
+            .filter(|h| h.name.eq_ignore_ascii_case("Connection"))
+            .flat_map(|h| h.value.split(','))
+            .map(|option| option.trim().to_ascii_lowercase());
+
+        let mut keep_alive = false;
+        for option in options {
+            match option.as_str() {
+                "close" => return false,
+                "keep-alive" => keep_alive = true,
+                _ => {}
+            }
+        }
+        self.version == "HTTP/1.1" || keep_alive
+    }
+
+    /// https://httpwg.org/specs/rfc9110.html#status.3xx
+    pub fn is_redirect(&self) -> bool {
+        matches!(self.status_code, 301 | 302 | 303 | 307 | 308)
+    }
+
+    /// https://httpwg.org/specs/rfc9110.html#field.location
+    /// リダイレクトのレスポンスであれば、Locationヘッダの値を`url`(リクエストしたURL)を
+    /// 基準に解決した遷移先を返す。相対パスの場合、元のURLのクエリは引き継がない
+    pub fn redirect_url(&self, url: &Url) -> Result<Option<Url>, Error> {
+        if !self.is_redirect() {
+            return Ok(None);
+        }
+
+        let location = match self.header_value("Location") {
+            Ok(l) => l,
+            Err(e) => return Err(Error::UnexpectedInput(e)),
+        };
+
+        match url.join(&location) {
+            Ok(u) => Ok(Some(u)),
+            Err(e) => Err(Error::UnexpectedInput(format!(
+                "invalid redirect location {}: {}",
+                location, e
+            ))),
+        }
+    }
+}
+
+/// ヘッダの終わりを表す空行を探し、ヘッダの終わりの位置とボディの始まりの位置を返す
+fn find_end_of_headers(raw_response: &[u8]) -> Option<(usize, usize)> {
+    for i in 0..raw_response.len() {
+        if raw_response[i..].starts_with(b"\r\n\r\n") {
+            return Some((i, i + 4));
+        }
+        if raw_response[i..].starts_with(b"\n\n") {
+            return Some((i, i + 2));
+        }
+    }
+    None
+}
+
+/// https://mimesniff.spec.whatwg.org/#rules-for-identifying-an-unknown-mime-type
+/// 先頭の空白を除いて、HTMLのタグやコメントで始まる場合のみ"text/html"とみなす。
+/// 誤ってHTMLとして扱わないように控えめに判定し、それ以外はバイナリを表すバイトの有無で
+/// "application/octet-stream"か"text/plain"を返す
+fn sniff_mime_type(body: &[u8]) -> &'static str {
+    const HTML_PATTERNS: [&[u8]; 17] = [
+        b"<!DOCTYPE HTML",
+        b"<HTML",
+        b"<HEAD",
+        b"<SCRIPT",
+        b"<IFRAME",
+        b"<H1",
+        b"<DIV",
+        b"<FONT",
+        b"<TABLE",
+        b"<A",
+        b"<STYLE",
+        b"<TITLE",
+        b"<B",
+        b"<BODY",
+        b"<BR",
+        b"<P",
+        b"<!--",
+    ];
+
+    let start = body
+        .iter()
+        .position(|b| !matches!(b, b'\t' | b'\n' | 0x0c | b'\r' | b' '))
+        .unwrap_or(body.len());
+    let rest = &body[start..];
+    for pattern in HTML_PATTERNS {
+        // パターンの直後はタグの区切り(空白か`>`)でなければならない
+        if rest.len() > pattern.len()
+            && rest[..pattern.len()].eq_ignore_ascii_case(pattern)
+            && matches!(rest[pattern.len()], b' ' | b'>')
+        {
+            return "text/html";
+        }
+    }
+
+    let is_binary = body
+        .iter()
+        .any(|b| matches!(b, 0x00..=0x08 | 0x0b | 0x0e..=0x1a | 0x1c..=0x1f));
+    if is_binary {
+        "application/octet-stream"
+    } else {
+        "text/plain"
+    }
+}
+
+/// レスポンスのボディのバイト数の既定の上限
+pub const DEFAULT_MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// ステータス行とヘッダのバイト数の上限。ヘッダの終わりの空行を送らないサーバに対して、
+/// 受信したデータが際限なく増えないようにする
+pub const MAX_HEADER_SIZE: usize = 64 * 1024;
+
+/// `read`でレスポンスを読み込む。ヘッダからボディの長さが決まる場合(body_lengthを参照)は
+/// その分だけを、決まらない場合は`read`が0を返すまでを読む。接続を使い回す場合も、
+/// 次のレスポンスを待って読み込みが止まらない。ヘッダがMAX_HEADER_SIZEバイトを、
+/// ボディが`max_body_size`バイトを超えた時点で読み込みをやめ、エラーを返す
+pub fn read_response<F>(mut read: F, max_body_size: usize) -> Result<Vec<u8>, Error>
+where
+    F: FnMut(&mut [u8]) -> Result<usize, Error>,
+{
+    let mut received = Vec::new();
+    let mut start_of_body = None;
+    let mut body_length = None;
+    // ヘッダの終わりを探し終えた位置。空行が読み込みの境目にまたがっても見つかるように、
+    // 次は区切りの長さの分だけ手前から探す
+    let mut scanned: usize = 0;
+    loop {
+        let mut buf = [0u8; 4096];
+        let bytes_read = read(&mut buf)?;
+        if bytes_read == 0 {
+            return Ok(received);
+        }
+        received.extend_from_slice(&buf[..bytes_read]);
+
+        if start_of_body.is_none() {
+            let from = scanned.saturating_sub(3);
+            start_of_body =
+                find_end_of_headers(&received[from..]).map(|(_, start)| from + start);
+            scanned = received.len();
+            match start_of_body {
+                Some(start) => {
+                    body_length = HttpResponse::from_bytes(&received[..start])?.body_length()
+                }
+                None if received.len() > MAX_HEADER_SIZE => {
+                    return Err(Error::Network("response header too large".to_string()));
+                }
+                None => {}
+            }
+        }
+        if let Some(start) = start_of_body {
+            let length = body_length.unwrap_or(received.len() - start);
+            if length > max_body_size {
+                return Err(Error::Network("response too large".to_string()));
+            }
+            if received.len() - start >= length && body_length.is_some() {
+                received.truncate(start + length);
+                return Ok(received);
+            }
+        }
+    }
+}
+
+/// https://fetch.spec.whatwg.org/#http-redirect-fetch
+/// リダイレクトを辿る回数の上限
+pub const MAX_REDIRECTS: usize = 20;
+
+/// リダイレクトを辿った結果のレスポンスと、リクエストしたURL・最終的なURL
+#[derive(Debug, Clone)]
+pub struct RedirectedResponse {
+    original_url: Url,
+    url: Url,
+    response: HttpResponse,
+}
+
+impl RedirectedResponse {
+    /// 最初にリクエストしたURL
+    pub fn original_url(&self) -> Url {
+        self.original_url.clone()
+    }
+
+    /// リダイレクトを全て辿った後の、レスポンスを返したURL。
+    /// 最終的なページ内の相対リンクはこのURLを基準に解決する
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    pub fn response(&self) -> HttpResponse {
+        self.response.clone()
+    }
+}
+
+/// `fetch`でレスポンスを取得し、リダイレクトであればLocationヘッダの遷移先を
+/// MAX_REDIRECTS回まで辿る
+pub fn follow_redirects<F>(url: Url, mut fetch: F) -> Result<RedirectedResponse, Error>
+where
+    F: FnMut(&Url) -> Result<HttpResponse, Error>,
+{
+    let mut current = url.clone();
+
+    for _ in 0..=MAX_REDIRECTS {
+        let response = fetch(&current)?;
+        match response.redirect_url(&current)? {
+            Some(next) => current = next,
+            None => {
+                return Ok(RedirectedResponse {
+                    original_url: url,
+                    url: current,
+                    response,
+                })
+            }
+        }
+    }
+
+    Err(Error::Network(format!(
+        "too many redirects from {}{}",
+        url.host(),
+        url.path()
+    )))
+}
+
+/// 通信の失敗(Error::Network)か5xxのレスポンスであれば、`fetch`を最大`retries`回まで
+/// やり直す。4xxやリダイレクトのレスポンス、その他のエラーはそのまま返す。
+/// やり直しても成功しなかった場合は、最後の結果(エラーまたは5xxのレスポンス)を返す
+pub fn retry<F>(retries: usize, mut fetch: F) -> Result<HttpResponse, Error>
+where
+    F: FnMut() -> Result<HttpResponse, Error>,
+{
+    let mut result = fetch();
+    for _ in 0..retries {
+        let transient = match result {
+            Ok(ref response) => (500..600).contains(&response.status_code()),
+            Err(Error::Network(_)) => true,
+            Err(_) => false,
+        };
+        if !transient {
+            break;
+        }
+        result = fetch();
+    }
+    result
+}
+
+/// HTTPのリクエストを送り、レスポンスを受け取る接続。HttpClientはTcpStreamを包んだ実装を使い、
+/// テストでは決まったレスポンスを返す実装に差し替えられる
+pub trait Connection {
+    /// 受信したデータを`buf`に読み込み、そのバイト数を返す。接続が閉じられていれば0を返す
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+    /// `buf`の先頭から送信し、送信できたバイト数を返す
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error>;
+}
+
+/// 最後に使った接続を1つ、接続先のホストとポートと一緒に保持し、同じ接続先への次の
+/// リクエストでも使い回す
+#[derive(Debug)]
+pub struct KeepAlive<C> {
+    connection: Option<(String, u16, C)>,
+}
+
+impl<C> Default for KeepAlive<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> KeepAlive<C> {
+    pub fn new() -> Self {
+        Self { connection: None }
+    }
+}
+
+impl<C: Connection> KeepAlive<C> {
+    /// `host`と`port`への使い回せる接続を保持している場合はtrue
+    pub fn has_connection(&self, host: &str, port: u16) -> bool {
+        matches!(self.connection, Some((ref h, p, _)) if h == host && p == port)
+    }
+
+    /// `request`のホストと`port`への接続を保持していればそれを、なければ`connect`で作った
+    /// 接続を使って`request`を送り、レスポンスを読み込む。
+    ///
+    /// 送信後も接続を保持するのは、`request`がkeep_aliveで、レスポンスがis_persistentであり、
+    /// ボディの長さがヘッダから決まる場合だけ。サーバがConnection: closeを返した場合や、
+    /// 送受信に失敗した場合は、閉じられたかもしれない接続から次のレスポンスを読まないように
+    /// 接続を捨てる
+    pub fn send<F>(
+        &mut self,
+        port: u16,
+        request: &HttpRequest,
+        max_body_size: usize,
+        connect: F,
+    ) -> Result<HttpResponse, Error>
+    where
+        F: FnOnce() -> Result<C, Error>,
+    {
+        let host = request.host();
+        let mut connection = match self.connection.take() {
+            Some((h, p, connection)) if h == host && p == port => connection,
+            _ => connect()?,
+        };
+
+        let serialized = request.serialize();
+        let mut bytes = serialized.as_bytes();
+        while !bytes.is_empty() {
+            match connection.write(bytes)? {
+                0 => return Err(Error::Network("connection closed while sending".to_string())),
+                n => bytes = &bytes[n..],
+            }
+        }
+
+        let received = read_response(|buf| connection.read(buf), max_body_size)?;
+        let response = HttpResponse::from_bytes(&received)?;
+        if request.keep_alive() && response.is_persistent() && response.body_length().is_some()
+        {
+            self.connection = Some((host, port, connection));
+        }
+        Ok(response)
+    }
+}
+
+/// ホスト名からIPアドレスを引く。HttpClientはこれを通して名前解決するので、
+/// テストでは決まったアドレスを返す実装に差し替えられる
+pub trait Resolver {
+    fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, Error>;
+}
+
+/// `host`を`resolver`で名前解決し、最初のアドレスと`port`の組を返す。
+/// アドレスが1つも見つからない場合はError::Networkを返す
+pub fn resolve_socket_addr(
+    resolver: &dyn Resolver,
+    host: &str,
+    port: u16,
+) -> Result<SocketAddr, Error> {
+    match resolver.lookup(host)?.first() {
+        Some(ip) => Ok(SocketAddr::new(*ip, port)),
+        None => Err(Error::Network("Failed to find IP addresses".to_string())),
+    }
+}
+
+/// 送信したリクエストと受信したレスポンスを、デバッグのためにコールバックに渡す。
+/// ボディは量が多くなりやすいため、verboseが設定されている場合のみ渡す
+#[derive(Clone)]
+pub struct HttpLogger {
+    callback: Rc<dyn Fn(&str)>,
+    verbose: bool,
+}
+
+impl HttpLogger {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&str) + 'static,
+    {
+        Self {
+            callback: Rc::new(callback),
+            verbose: false,
+        }
+    }
+
+    pub fn verbose(&self) -> bool {
+        self.verbose
+    }
+
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    /// リクエスト行とヘッダを渡す。verboseの場合はボディも続けて渡す
+    pub fn log_request(&self, request: &HttpRequest) {
+        let mut message = request.serialize_head();
+        if self.verbose {
+            if let Some(ref body) = request.body {
+                message.push_str(body);
+            }
+        }
+        (self.callback)(&message);
+    }
+
+    /// ステータス行とヘッダを渡す。verboseの場合はボディも続けて渡す
+    pub fn log_response(&self, response: &HttpResponse) {
+        let mut message = format!(
+            "{} {} {}\n",
+            response.version, response.status_code, response.reason
+        );
+        for h in &response.headers {
+            message.push_str(&format!("{}: {}\n", h.name, h.value));
+        }
+        message.push('\n');
+        if self.verbose {
+            message.push_str(&response.body);
+        }
+        (self.callback)(&message);
+    }
+}
+
+impl fmt::Debug for HttpLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpLogger")
+            .field("verbose", &self.verbose)
+            .finish()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::vec;
+    use core::cell::RefCell;
 
     #[test]
     fn test_invalid() {
@@ -150,4 +789,653 @@ mod tests {
 
         assert_eq!(res.body(), "body message".to_string());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_from_bytes_latin1() {
+        let mut raw = b"HTTP/1.1 200 OK\nContent-Type: text/html; charset=ISO-8859-1\n\n".to_vec();
+        raw.extend_from_slice(&[0x63, 0x61, 0x66, 0xE9]);
+        let res = HttpResponse::from_bytes(&raw).expect("failed to parse http response");
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(res.body(), "café");
+    }
+
+    #[test]
+    fn test_from_bytes_utf8() {
+        let raw = "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n日本語".as_bytes();
+        let res = HttpResponse::from_bytes(raw).expect("failed to parse http response");
+        assert_eq!(res.header_value("Content-Type"), Ok("text/html".to_string()));
+        assert_eq!(res.body(), "日本語");
+    }
+
+    #[test]
+    fn test_request() {
+        let request = HttpRequest::new("example.com".to_string(), "index.html".to_string());
+        let serialized = request.serialize();
+        assert_eq!(
+            "GET /index.html HTTP/1.1\nHost: example.com\nAccept: text/html\nConnection: close\n\n",
+            serialized
+        );
+        assert!(!serialized.contains("Accept-Language"));
+    }
+
+    #[test]
+    fn test_http10_request() {
+        let mut request = HttpRequest::new("example.com".to_string(), "index.html".to_string());
+        request.set_version(Version::Http10);
+        assert_eq!(
+            "GET /index.html HTTP/1.0\nAccept: text/html\nConnection: close\n\n",
+            request.serialize()
+        );
+
+        let response = HttpResponse::new("HTTP/1.0 200 OK\nContent-Type: text/html\n\nbody".to_string())
+            .expect("failed to parse http response");
+        assert_eq!("HTTP/1.0", response.version());
+        assert_eq!(200, response.status_code());
+        assert_eq!("OK", response.reason());
+        assert_eq!("body", response.body());
+        assert!(!response.is_persistent());
+    }
+
+    #[test]
+    fn test_request_accept_language() {
+        let mut request = HttpRequest::new("example.com".to_string(), "".to_string());
+        request.add_header(
+            "Accept-Language".to_string(),
+            "ja, en-US;q=0.8, en;q=0.5".to_string(),
+        );
+        assert_eq!(
+            "GET / HTTP/1.1\nHost: example.com\nAccept: text/html\nAccept-Language: ja, en-US;q=0.8, en;q=0.5\nConnection: close\n\n",
+            request.serialize()
+        );
+    }
+
+    #[test]
+    fn test_post_request() {
+        let mut request = HttpRequest::new("example.com".to_string(), "submit".to_string());
+        request.set_method(Method::Post);
+        request.add_header(
+            "Content-Type".to_string(),
+            "application/x-www-form-urlencoded".to_string(),
+        );
+        request.set_body("a=1&b=2".to_string());
+        assert_eq!(
+            "POST /submit HTTP/1.1\nHost: example.com\nAccept: text/html\nContent-Type: application/x-www-form-urlencoded\nContent-Length: 7\nConnection: close\n\na=1&b=2",
+            request.serialize()
+        );
+    }
+
+    fn redirect(location: &str) -> HttpResponse {
+        let raw = format!("HTTP/1.1 302 Found\nLocation: {}\n\n", location);
+        HttpResponse::new(raw).expect("failed to parse http response")
+    }
+
+    fn request_url() -> Url {
+        Url::new("http://example.com:8888/dir/index.html?a=123".to_string())
+            .parse()
+            .expect("failed to parse url")
+    }
+
+    #[test]
+    fn test_redirect_relative_location() {
+        let url = redirect("next.html")
+            .redirect_url(&request_url())
+            .expect("failed to resolve location")
+            .expect("response should be a redirect");
+        assert_eq!("example.com", url.host());
+        assert_eq!("8888", url.port());
+        assert_eq!("dir/next.html", url.path());
+        // 元のURLのクエリは引き継がない
+        assert_eq!("", url.searchpart());
+    }
+
+    #[test]
+    fn test_redirect_root_relative_location() {
+        let url = redirect("/top.html?b=456")
+            .redirect_url(&request_url())
+            .expect("failed to resolve location")
+            .expect("response should be a redirect");
+        assert_eq!("example.com", url.host());
+        assert_eq!("top.html", url.path());
+        assert_eq!("b=456", url.searchpart());
+    }
+
+    #[test]
+    fn test_redirect_absolute_location() {
+        let url = redirect("http://other.com/index.html")
+            .redirect_url(&request_url())
+            .expect("failed to resolve location")
+            .expect("response should be a redirect");
+        assert_eq!("other.com", url.host());
+        assert_eq!("80", url.port());
+        assert_eq!("index.html", url.path());
+        assert_eq!("", url.searchpart());
+    }
+
+    #[test]
+    fn test_not_redirect() {
+        let raw = "HTTP/1.1 200 OK\nLocation: next.html\n\n".to_string();
+        let res = HttpResponse::new(raw).expect("failed to parse http response");
+        assert_eq!(Ok(None), res.redirect_url(&request_url()));
+    }
+
+    #[test]
+    fn test_redirect_without_location() {
+        let raw = "HTTP/1.1 301 Moved Permanently\nDate: xx xx xx\n\n".to_string();
+        let res = HttpResponse::new(raw).expect("failed to parse http response");
+        assert!(res.redirect_url(&request_url()).is_err());
+    }
+
+    fn fake_fetch(url: &Url) -> Result<HttpResponse, Error> {
+        let raw = match url.path().as_str() {
+            "a" => "HTTP/1.1 301 Moved\nLocation: /b?x=1\n\n".to_string(),
+            "b" => "HTTP/1.1 302 Found\nLocation: http://other.com/c\n\n".to_string(),
+            "c" => "HTTP/1.1 200 OK\nDate: xx xx xx\n\nfinal".to_string(),
+            "loop" => "HTTP/1.1 302 Found\nLocation: loop\n\n".to_string(),
+            _ => "HTTP/1.1 404 NotFound\nDate: xx xx xx\n\n".to_string(),
+        };
+        HttpResponse::new(raw)
+    }
+
+    #[test]
+    fn test_follow_redirects() {
+        let url = Url::new("http://example.com/a".to_string())
+            .parse()
+            .expect("failed to parse url");
+        let mut hops = 0;
+        let res = follow_redirects(url, |u| {
+            hops += 1;
+            fake_fetch(u)
+        })
+        .expect("failed to follow redirects");
+
+        assert_eq!(3, hops);
+        assert_eq!("example.com", res.original_url().host());
+        assert_eq!("a", res.original_url().path());
+        assert_eq!("other.com", res.url().host());
+        assert_eq!("c", res.url().path());
+        assert_eq!("final", res.response().body());
+    }
+
+    #[test]
+    fn test_follow_redirects_without_redirect() {
+        let url = Url::new("http://example.com/c".to_string())
+            .parse()
+            .expect("failed to parse url");
+        let res = follow_redirects(url.clone(), fake_fetch).expect("failed to follow redirects");
+        assert_eq!(url, res.original_url());
+        assert_eq!(url, res.url());
+    }
+
+    #[test]
+    fn test_too_many_redirects() {
+        let url = Url::new("http://example.com/loop".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert!(follow_redirects(url, fake_fetch).is_err());
+    }
+
+    fn chunked_reader(data: Vec<u8>, chunk: usize) -> impl FnMut(&mut [u8]) -> Result<usize, Error> {
+        let mut pos = 0;
+        move |buf| {
+            let n = chunk.min(buf.len()).min(data.len() - pos);
+            buf[..n].copy_from_slice(&data[pos..pos + n]);
+            pos += n;
+            Ok(n)
+        }
+    }
+
+    fn response_with_body(len: usize) -> Vec<u8> {
+        let mut raw = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n".to_vec();
+        raw.resize(raw.len() + len, b'a');
+        raw
+    }
+
+    #[test]
+    fn test_read_response_under_limit() {
+        let raw = response_with_body(10);
+        let received =
+            read_response(chunked_reader(raw.clone(), 3), 10).expect("failed to read a response");
+        assert_eq!(raw, received);
+    }
+
+    #[test]
+    fn test_read_response_over_limit() {
+        let raw = response_with_body(11);
+        match read_response(chunked_reader(raw, 3), 10) {
+            Err(Error::Network(message)) => assert_eq!("response too large", message),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_read_response_header_over_limit() {
+        // ヘッダの終わりの空行を送らずにデータを送り続けるサーバ
+        let mut total = 0;
+        let endless = |buf: &mut [u8]| {
+            buf.fill(b'a');
+            total += buf.len();
+            Ok(buf.len())
+        };
+        match read_response(endless, 10) {
+            Err(Error::Network(message)) => assert_eq!("response header too large", message),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(total <= MAX_HEADER_SIZE + 4096);
+    }
+
+    fn recording_logger() -> (HttpLogger, Rc<RefCell<Vec<String>>>) {
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let recorded = messages.clone();
+        let logger = HttpLogger::new(move |m| recorded.borrow_mut().push(m.to_string()));
+        (logger, messages)
+    }
+
+    #[test]
+    fn test_logger() {
+        let (logger, messages) = recording_logger();
+        let mut request = HttpRequest::new("example.com".to_string(), "submit".to_string());
+        request.set_method(Method::Post);
+        request.set_body("a=1".to_string());
+        logger.log_request(&request);
+        let response = HttpResponse::new(
+            "HTTP/1.1 404 NotFound\nContent-Type: text/html\n\nmissing".to_string(),
+        )
+        .expect("failed to parse http response");
+        logger.log_response(&response);
+
+        let messages = messages.borrow();
+        assert_eq!(2, messages.len());
+        assert!(messages[0].starts_with("POST /submit HTTP/1.1\n"));
+        assert!(!messages[0].contains("a=1"));
+        assert_eq!(
+            "HTTP/1.1 404 NotFound\nContent-Type: text/html\n\n",
+            messages[1]
+        );
+    }
+
+    #[test]
+    fn test_verbose_logger() {
+        let (mut logger, messages) = recording_logger();
+        logger.set_verbose(true);
+        let mut request = HttpRequest::new("example.com".to_string(), "submit".to_string());
+        request.set_body("a=1".to_string());
+        logger.log_request(&request);
+        let response = HttpResponse::new("HTTP/1.1 200 OK\n\nbody".to_string())
+            .expect("failed to parse http response");
+        logger.log_response(&response);
+
+        let messages = messages.borrow();
+        assert_eq!(request.serialize(), messages[0]);
+        assert!(messages[1].ends_with("\n\nbody"));
+    }
+
+    fn status_response(status: u32) -> HttpResponse {
+        HttpResponse::new(format!("HTTP/1.1 {} Status\n\nbody", status))
+            .expect("failed to parse http response")
+    }
+
+    #[test]
+    fn test_retry_until_success() {
+        let mut attempts = 0;
+        let res = retry(3, || {
+            attempts += 1;
+            match attempts {
+                1 => Err(Error::Network("connection refused".to_string())),
+                2 => Ok(status_response(503)),
+                _ => Ok(status_response(200)),
+            }
+        })
+        .expect("failed to retry");
+        assert_eq!(3, attempts);
+        assert_eq!(200, res.status_code());
+    }
+
+    #[test]
+    fn test_retry_limit() {
+        let mut attempts = 0;
+        let res = retry(2, || {
+            attempts += 1;
+            Err(Error::Network("connection refused".to_string()))
+        });
+        assert_eq!(3, attempts);
+        match res {
+            Err(Error::Network(message)) => assert_eq!("connection refused", message),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_retry_not_transient() {
+        for status in [404, 302] {
+            let mut attempts = 0;
+            let res = retry(3, || {
+                attempts += 1;
+                Ok(status_response(status))
+            })
+            .expect("failed to fetch");
+            assert_eq!(1, attempts);
+            assert_eq!(status, res.status_code());
+        }
+
+        let mut attempts = 0;
+        let res = retry(3, || {
+            attempts += 1;
+            Err(Error::UnexpectedInput("invalid port".to_string()))
+        });
+        assert_eq!(1, attempts);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_body_as_utf8_lossy() {
+        let mut raw = b"HTTP/1.1 200 OK\nContent-Type: text/plain; charset=utf-8\n\n".to_vec();
+        raw.extend_from_slice("日本".as_bytes());
+        let res = HttpResponse::from_bytes(&raw).expect("failed to parse http response");
+        assert_eq!("日本", res.body_as_utf8_lossy());
+        assert_eq!("日本".as_bytes(), res.body_bytes());
+
+        let mut raw = b"HTTP/1.1 200 OK\nContent-Type: image/png\n\n".to_vec();
+        raw.extend_from_slice(&[b'a', 0xff, 0xfe, b'b']);
+        let res = HttpResponse::from_bytes(&raw).expect("failed to parse http response");
+        assert_eq!("a\u{FFFD}\u{FFFD}b", res.body_as_utf8_lossy());
+        assert_eq!([b'a', 0xff, 0xfe, b'b'].to_vec(), res.body_bytes());
+    }
+
+    #[test]
+    fn test_content_type_sniffing() {
+        let sniffed = |raw: &[u8]| {
+            HttpResponse::from_bytes(raw)
+                .expect("failed to parse http response")
+                .content_type()
+        };
+        assert_eq!(
+            "text/html",
+            sniffed(b"HTTP/1.1 200 OK\nDate: xx\n\n\n  <!doctype html><html></html>")
+        );
+        assert_eq!("text/html", sniffed(b"HTTP/1.1 200 OK\nDate: xx\n\n<HTML>"));
+        assert_eq!("text/html", sniffed(b"HTTP/1.1 200 OK\nDate: xx\n\n<p class=a>x</p>"));
+        // `<p`の後がタグの区切りでなければHTMLとみなさない
+        assert_eq!("text/plain", sniffed(b"HTTP/1.1 200 OK\nDate: xx\n\n<pre>x</pre>"));
+        assert_eq!("text/plain", sniffed(b"HTTP/1.1 200 OK\nDate: xx\n\nhello <html>"));
+        assert_eq!(
+            "application/octet-stream",
+            sniffed(b"HTTP/1.1 200 OK\nDate: xx\n\n\x89PNG\r\n\x1a\n")
+        );
+        // ヘッダがあればそのまま使う
+        assert_eq!(
+            "text/plain",
+            sniffed(b"HTTP/1.1 200 OK\nContent-Type: text/plain\n\n<html>")
+        );
+    }
+
+    struct FakeResolver {
+        addresses: Vec<IpAddr>,
+    }
+
+    impl Resolver for FakeResolver {
+        fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, Error> {
+            if host == "unknown.example" {
+                return Err(Error::Network(format!("unknown host {}", host)));
+            }
+            Ok(self.addresses.clone())
+        }
+    }
+
+    #[test]
+    fn test_resolve_socket_addr() {
+        let resolver = FakeResolver {
+            addresses: vec![
+                IpAddr::from([192, 0, 2, 1]),
+                IpAddr::from([192, 0, 2, 2]),
+            ],
+        };
+        assert_eq!(
+            Ok(SocketAddr::from(([192, 0, 2, 1], 8000))),
+            resolve_socket_addr(&resolver, "example.com", 8000)
+        );
+        match resolve_socket_addr(&resolver, "unknown.example", 80) {
+            Err(Error::Network(message)) => assert_eq!("unknown host unknown.example", message),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let resolver = FakeResolver {
+            addresses: Vec::new(),
+        };
+        match resolve_socket_addr(&resolver, "example.com", 80) {
+            Err(Error::Network(message)) => assert_eq!("Failed to find IP addresses", message),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_is_persistent() {
+        let response = |raw: &str| HttpResponse::new(raw.to_string()).expect("failed to parse");
+
+        assert!(response("HTTP/1.1 200 OK\n\nbody").is_persistent());
+        assert!(response("HTTP/1.1 200 OK\nConnection: keep-alive\n\nbody").is_persistent());
+        assert!(!response("HTTP/1.1 200 OK\nConnection: Close\n\nbody").is_persistent());
+        assert!(!response("HTTP/1.1 200 OK\nconnection: keep-alive, close\n\nbody").is_persistent());
+        assert!(!response("HTTP/1.0 200 OK\n\nbody").is_persistent());
+        assert!(response("HTTP/1.0 200 OK\nConnection: Keep-Alive\n\nbody").is_persistent());
+    }
+
+    /// テスト用のサーバ。リクエストを受け取るたびに、用意したレスポンスを順に返す。
+    /// レスポンスと一緒に、返した後に接続を閉じるかどうかを持つ
+    struct FakeServer {
+        responses: Vec<(&'static str, bool)>,
+        /// 受け取ったリクエストと、それを受け取った接続の番号
+        requests: Vec<(usize, String)>,
+        connections: usize,
+    }
+
+    struct FakeConnection {
+        id: usize,
+        server: Rc<RefCell<FakeServer>>,
+        pending: Vec<u8>,
+        closed: bool,
+    }
+
+    impl Connection for FakeConnection {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            if self.pending.is_empty() {
+                if self.closed {
+                    return Ok(0);
+                }
+                // 実際のソケットでは、サーバが接続を閉じない限りここで読み込みが止まる
+                return Err(Error::Network("read would block".to_string()));
+            }
+            let n = buf.len().min(self.pending.len());
+            buf[..n].copy_from_slice(&self.pending[..n]);
+            self.pending.drain(..n);
+            Ok(n)
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            if self.closed {
+                return Err(Error::Network("connection closed".to_string()));
+            }
+            let mut server = self.server.borrow_mut();
+            server
+                .requests
+                .push((self.id, String::from_utf8_lossy(buf).to_string()));
+            let (response, close) = server.responses.remove(0);
+            self.pending.extend_from_slice(response.as_bytes());
+            self.closed = close;
+            Ok(buf.len())
+        }
+    }
+
+    fn fake_server(responses: &[(&'static str, bool)]) -> Rc<RefCell<FakeServer>> {
+        Rc::new(RefCell::new(FakeServer {
+            responses: responses.to_vec(),
+            requests: Vec::new(),
+            connections: 0,
+        }))
+    }
+
+    fn connect(server: &Rc<RefCell<FakeServer>>) -> Result<FakeConnection, Error> {
+        server.borrow_mut().connections += 1;
+        Ok(FakeConnection {
+            id: server.borrow().connections,
+            server: server.clone(),
+            pending: Vec::new(),
+            closed: false,
+        })
+    }
+
+    /// `host`に`paths`のリクエストを順に送り、それぞれを受け取った接続の番号を返す
+    fn send_all(
+        keep_alive: &mut KeepAlive<FakeConnection>,
+        server: &Rc<RefCell<FakeServer>>,
+        host: &str,
+        paths: &[&str],
+    ) -> Vec<usize> {
+        let first = server.borrow().requests.len();
+        for path in paths {
+            let mut request = HttpRequest::new(host.to_string(), path.to_string());
+            request.set_keep_alive(true);
+            let response = keep_alive
+                .send(80, &request, DEFAULT_MAX_BODY_SIZE, || connect(server))
+                .expect("failed to send a request");
+            assert_eq!(200, response.status_code());
+        }
+        server.borrow().requests[first..]
+            .iter()
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    #[test]
+    fn test_keep_alive_reuses_connection() {
+        let server = fake_server(&[
+            ("HTTP/1.1 200 OK\nContent-Length: 1\nConnection: keep-alive\n\na", false),
+            ("HTTP/1.1 200 OK\nContent-Length: 2\n\nbc", false),
+            ("HTTP/1.0 200 OK\nContent-Length: 1\nConnection: Keep-Alive\n\nd", false),
+        ]);
+        let mut keep_alive = KeepAlive::new();
+        assert_eq!(
+            vec![1, 1, 1],
+            send_all(&mut keep_alive, &server, "example.com", &["a", "b", "c"])
+        );
+        assert!(keep_alive.has_connection("example.com", 80));
+
+        let requests = &server.borrow().requests;
+        assert_eq!(
+            "GET /b HTTP/1.1\nHost: example.com\nAccept: text/html\nConnection: keep-alive\n\n",
+            requests[1].1
+        );
+    }
+
+    #[test]
+    fn test_keep_alive_connection_close() {
+        let server = fake_server(&[
+            ("HTTP/1.1 200 OK\nContent-Length: 1\nConnection: close\n\na", true),
+            ("HTTP/1.1 200 OK\nContent-Length: 1\n\nb", false),
+            ("HTTP/1.1 200 OK\nContent-Length: 1\nConnection: keep-alive, close\n\nc", true),
+            ("HTTP/1.0 200 OK\nContent-Length: 1\n\nd", true),
+            // Content-Lengthがないと、ボディの終わりは接続が閉じられて分かる
+            ("HTTP/1.1 200 OK\n\ne", true),
+            ("HTTP/1.1 200 OK\nContent-Length: 1\n\nf", false),
+        ]);
+        let mut keep_alive = KeepAlive::new();
+        assert_eq!(
+            vec![1, 2, 2, 3, 4, 5],
+            send_all(
+                &mut keep_alive,
+                &server,
+                "example.com",
+                &["a", "b", "c", "d", "e", "f"]
+            )
+        );
+    }
+
+    #[test]
+    fn test_keep_alive_per_host() {
+        let server = fake_server(&[
+            ("HTTP/1.1 200 OK\nContent-Length: 1\n\na", false),
+            ("HTTP/1.1 200 OK\nContent-Length: 1\n\nb", false),
+            ("HTTP/1.1 200 OK\nContent-Length: 1\n\nc", false),
+        ]);
+        let mut keep_alive = KeepAlive::new();
+        assert_eq!(vec![1], send_all(&mut keep_alive, &server, "a.example", &["a"]));
+        assert!(!keep_alive.has_connection("b.example", 80));
+        assert_eq!(vec![2], send_all(&mut keep_alive, &server, "b.example", &["b"]));
+        assert!(!keep_alive.has_connection("a.example", 80));
+        assert_eq!(vec![3], send_all(&mut keep_alive, &server, "a.example", &["c"]));
+    }
+
+    #[test]
+    fn test_keep_alive_not_requested() {
+        let server = fake_server(&[
+            ("HTTP/1.1 200 OK\nContent-Length: 1\n\na", false),
+            ("HTTP/1.1 200 OK\nContent-Length: 1\n\nb", false),
+        ]);
+        let mut keep_alive = KeepAlive::new();
+        for path in ["a", "b"] {
+            let request = HttpRequest::new("example.com".to_string(), path.to_string());
+            keep_alive
+                .send(80, &request, DEFAULT_MAX_BODY_SIZE, || connect(&server))
+                .expect("failed to send a request");
+        }
+        let requests = &server.borrow().requests;
+        assert_eq!(vec![1, 2], requests.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+        assert!(requests[0].1.contains("Connection: close\n"));
+    }
+
+    #[test]
+    fn test_keep_alive_drops_failed_connection() {
+        let server = fake_server(&[
+            ("HTTP/1.1 200 OK\nContent-Length: 1\n\na", false),
+            // ヘッダの途中で止まったレスポンス
+            ("HTTP/1.1 200 OK\nContent-", false),
+            ("HTTP/1.1 200 OK\nContent-Length: 1\n\nc", false),
+        ]);
+        let mut keep_alive = KeepAlive::new();
+        assert_eq!(vec![1], send_all(&mut keep_alive, &server, "example.com", &["a"]));
+
+        let mut request = HttpRequest::new("example.com".to_string(), "b".to_string());
+        request.set_keep_alive(true);
+        let res = keep_alive.send(80, &request, DEFAULT_MAX_BODY_SIZE, || connect(&server));
+        assert_eq!(Err(Error::Network("read would block".to_string())), res.map(|_| ()));
+        assert!(!keep_alive.has_connection("example.com", 80));
+
+        assert_eq!(vec![2], send_all(&mut keep_alive, &server, "example.com", &["c"]));
+    }
+
+    #[test]
+    fn test_read_response_content_length() {
+        let mut chunks = vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhel".to_vec(),
+            b"lo, next response".to_vec(),
+        ];
+        let received = read_response(
+            |buf| {
+                if chunks.is_empty() {
+                    return Err(Error::Network("read would block".to_string()));
+                }
+                let chunk = chunks.remove(0);
+                buf[..chunk.len()].copy_from_slice(&chunk);
+                Ok(chunk.len())
+            },
+            DEFAULT_MAX_BODY_SIZE,
+        )
+        .expect("failed to read a response");
+        assert_eq!(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello".to_vec(), received);
+
+        let received = read_response(
+            |_| Err(Error::Network("read would block".to_string())),
+            DEFAULT_MAX_BODY_SIZE,
+        );
+        assert!(received.is_err());
+    }
+
+    #[test]
+    fn test_body_length() {
+        let response = |raw: &str| HttpResponse::new(raw.to_string()).expect("failed to parse");
+
+        assert_eq!(Some(4), response("HTTP/1.1 200 OK\ncontent-length: 4\n\nbody").body_length());
+        assert_eq!(None, response("HTTP/1.1 200 OK\n\nbody").body_length());
+        assert_eq!(Some(0), response("HTTP/1.1 204 No Content\n\n").body_length());
+        assert_eq!(Some(0), response("HTTP/1.1 304 Not Modified\n\n").body_length());
+    }
+}
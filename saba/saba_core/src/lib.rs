@@ -2,7 +2,12 @@
 
 extern crate alloc;
 
+pub mod browser;
+pub mod cache;
+pub mod data_url;
+pub mod encoding;
 pub mod error;
+pub mod file;
 pub mod http;
 pub mod renderer;
 pub mod url;
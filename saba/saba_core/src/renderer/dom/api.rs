@@ -0,0 +1,736 @@
+use crate::renderer::dom::node::Element;
+use crate::renderer::dom::node::ElementKind;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeId;
+use crate::renderer::dom::node::NodeKind;
+use crate::renderer::dom::node::Window;
+use alloc::string::String;
+use alloc::rc::Rc;
+use alloc::rc::Weak;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// https://dom.spec.whatwg.org/#dom-nonelementparentnode-getelementbyid
+/// `node`以下を文書順に探索し、最初に見つかった`id`を持つ要素を返す
+pub fn get_element_by_id(
+    node: Option<Rc<RefCell<Node>>>,
+    id: &str,
+) -> Option<Rc<RefCell<Node>>> {
+    let n = node?;
+
+    if let Some(element) = n.borrow().get_element() {
+        if element.get_attribute("id").as_deref() == Some(id) {
+            return Some(n.clone());
+        }
+    }
+
+    let mut child = n.borrow().first_child();
+    while let Some(c) = child {
+        if let Some(found) = get_element_by_id(Some(c.clone()), id) {
+            return Some(found);
+        }
+        child = c.borrow().next_sibling();
+    }
+
+    None
+}
+
+/// https://dom.spec.whatwg.org/#dom-node-appendchild
+/// `child`を`parent`の最後の子として追加する。`child`が既にツリーの中にある場合は、
+/// 元の親から取り除いてから追加する(ノードの移動)。`child`が`parent`自身かその祖先の
+/// 場合は、ツリーが循環してしまうので何もせずにfalseを返す
+pub fn append_child(parent: &Rc<RefCell<Node>>, child: Rc<RefCell<Node>>) -> bool {
+    // https://dom.spec.whatwg.org/#concept-node-ensure-pre-insertion-validity
+    let mut ancestor = Some(parent.clone());
+    while let Some(a) = ancestor {
+        if Rc::ptr_eq(&a, &child) {
+            return false;
+        }
+        ancestor = a.borrow().parent().upgrade();
+    }
+
+    // https://dom.spec.whatwg.org/#concept-node-adopt
+    let old_parent = child.borrow().parent().upgrade();
+    if let Some(old_parent) = old_parent {
+        remove_child(&old_parent, &child);
+    }
+    {
+        let mut c = child.borrow_mut();
+        c.set_previous_sibling(Weak::new());
+        c.set_next_sibling(None);
+    }
+
+    let last_child = parent.borrow().last_child().upgrade();
+    match last_child {
+        Some(last) => {
+            last.borrow_mut().set_next_sibling(Some(child.clone()));
+            child.borrow_mut().set_previous_sibling(Rc::downgrade(&last));
+        }
+        None => parent.borrow_mut().set_first_child(Some(child.clone())),
+    }
+
+    parent.borrow_mut().set_last_child(Rc::downgrade(&child));
+    child.borrow_mut().set_parent(Rc::downgrade(parent));
+    true
+}
+
+/// https://dom.spec.whatwg.org/#dom-node-removechild
+/// `child`を`parent`の子から取り除く。`child`が`parent`の子でなければfalseを返す
+pub fn remove_child(parent: &Rc<RefCell<Node>>, child: &Rc<RefCell<Node>>) -> bool {
+    let is_child = match child.borrow().parent().upgrade() {
+        Some(p) => Rc::ptr_eq(&p, parent),
+        None => false,
+    };
+    if !is_child {
+        return false;
+    }
+
+    let previous = child.borrow().precious_sibling().upgrade();
+    let next = child.borrow().next_sibling();
+
+    match previous {
+        Some(ref p) => p.borrow_mut().set_next_sibling(next.clone()),
+        None => parent.borrow_mut().set_first_child(next.clone()),
+    }
+    let previous_weak = match previous {
+        Some(ref p) => Rc::downgrade(p),
+        None => Weak::new(),
+    };
+    match next {
+        Some(ref n) => n.borrow_mut().set_previous_sibling(previous_weak),
+        None => parent.borrow_mut().set_last_child(previous_weak),
+    }
+
+    let mut c = child.borrow_mut();
+    c.set_parent(Weak::new());
+    c.set_previous_sibling(Weak::new());
+    c.set_next_sibling(None);
+    true
+}
+
+/// https://html.spec.whatwg.org/multipage/forms.html#categories
+/// `form`の子孫にあるフォームのコントロール(<input>、<select>、<textarea>)を文書順に返す。
+/// form属性による関連付けはサポートしていない
+pub fn form_controls(form: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    let mut controls = Vec::new();
+    let mut child = form.borrow().first_child();
+    while let Some(c) = child {
+        collect_form_controls(&c, &mut controls);
+        child = c.borrow().next_sibling();
+    }
+    controls
+}
+
+fn collect_form_controls(node: &Rc<RefCell<Node>>, result: &mut Vec<Rc<RefCell<Node>>>) {
+    match node.borrow().element_kind() {
+        Some(ElementKind::Input) | Some(ElementKind::Select) | Some(ElementKind::Textarea) => {
+            result.push(node.clone())
+        }
+        _ => {}
+    }
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        collect_form_controls(&c, result);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/input.html#the-input-element
+/// 文字を入力して編集できる<input>かどうか
+fn is_text_input(node: &Rc<RefCell<Node>>) -> bool {
+    match node.borrow().get_element() {
+        Some(e) if e.kind() == ElementKind::Input => matches!(
+            e.input_type().as_str(),
+            "text" | "search" | "password" | "email" | "url" | "tel"
+        ),
+        _ => false,
+    }
+}
+
+/// フォーカスされている<input>のカーソルの位置に`c`を挿入する。UIのキー入力から呼ばれる。
+/// 値が変わった場合はtrueを返すので、呼び出し側はレイアウトと描画をやり直す
+pub fn insert_text(node: &Rc<RefCell<Node>>, c: char) -> bool {
+    if !is_text_input(node) {
+        return false;
+    }
+    if let NodeKind::Element(ref mut e) = node.borrow_mut().kind {
+        e.insert_char(c);
+    }
+    true
+}
+
+/// フォーカスされている<input>のカーソルの直前の1文字を削除する。値が変わった場合はtrueを返す
+pub fn backspace(node: &Rc<RefCell<Node>>) -> bool {
+    if !is_text_input(node) {
+        return false;
+    }
+    match node.borrow_mut().kind {
+        NodeKind::Element(ref mut e) => e.delete_char_before_cursor(),
+        _ => false,
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/interaction.html#sequential-focus-navigation-order
+/// Tabキーでフォーカスが移る要素を、移る順に返す。tabindexが正の要素が値の小さい順に先に並び、
+/// 残りは文書順に並ぶ。tabindexが負の要素は含めない
+pub fn focusable_elements(node: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    let mut candidates = Vec::new();
+    collect_focusable_elements(node, &mut candidates);
+
+    // sort_by_keyは安定ソートなので、同じtabindexの要素は文書順のまま並ぶ
+    candidates.sort_by_key(|(tabindex, _)| if *tabindex > 0 { *tabindex } else { i32::MAX });
+    candidates.into_iter().map(|(_, n)| n).collect()
+}
+
+fn collect_focusable_elements(
+    node: &Rc<RefCell<Node>>,
+    result: &mut Vec<(i32, Rc<RefCell<Node>>)>,
+) {
+    if let Some(element) = node.borrow().get_element() {
+        if let Some(tabindex) = tabindex(&element) {
+            if tabindex >= 0 {
+                result.push((tabindex, node.clone()));
+            }
+        }
+    }
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        collect_focusable_elements(&c, result);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/interaction.html#attr-tabindex
+/// フォーカスできない要素はNoneを返す。type=hiddenの<input>と、disabled属性のある
+/// フォームのコントロールは、tabindexがあってもフォーカスできない
+fn tabindex(element: &Element) -> Option<i32> {
+    let is_control = matches!(
+        element.kind(),
+        ElementKind::Input | ElementKind::Select | ElementKind::Textarea
+    );
+    if is_control && element.get_attribute("disabled").is_some() {
+        return None;
+    }
+    let is_hidden_input = element.kind() == ElementKind::Input
+        && element
+            .get_attribute("type")
+            .is_some_and(|t| t.trim().eq_ignore_ascii_case("hidden"));
+    if is_hidden_input {
+        return None;
+    }
+
+    if let Some(value) = element.get_attribute("tabindex") {
+        if let Ok(tabindex) = value.trim().parse::<i32>() {
+            return Some(tabindex);
+        }
+    }
+
+    match element.kind() {
+        ElementKind::A if element.get_attribute("href").is_some() => Some(0),
+        ElementKind::Input | ElementKind::Select | ElementKind::Textarea => Some(0),
+        _ => None,
+    }
+}
+
+/// ページ内検索。`root`以下のテキストノードから`query`に一致する箇所を、大文字と小文字を
+/// 区別せずに探し、テキストノードのIDと文字単位のオフセットを文書順に返す。
+/// 重なり合う一致は最初のものだけを返し、複数のテキストノードにまたがる一致は見つけられない
+pub fn find_all(root: &Rc<RefCell<Node>>, query: &str) -> Vec<(NodeId, usize)> {
+    let mut result = Vec::new();
+    if query.is_empty() {
+        return result;
+    }
+    let query: Vec<char> = query.chars().collect();
+    collect_matches(root, &query, &mut result);
+    result
+}
+
+fn collect_matches(node: &Rc<RefCell<Node>>, query: &[char], result: &mut Vec<(NodeId, usize)>) {
+    if let NodeKind::Text(ref text) = node.borrow().kind() {
+        let text: Vec<char> = text.chars().collect();
+        let mut offset = 0;
+        while offset + query.len() <= text.len() {
+            let matched = text[offset..offset + query.len()]
+                .iter()
+                .zip(query)
+                .all(|(a, b)| a.to_lowercase().eq(b.to_lowercase()));
+            if matched {
+                result.push((node.borrow().id(), offset));
+                offset += query.len();
+            } else {
+                offset += 1;
+            }
+        }
+    }
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        collect_matches(&c, query, result);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// https://dom.spec.whatwg.org/#dom-node-textcontent
+/// `node`以下の全てのテキストノードの内容を文書順に連結して返す
+pub fn text_content(node: &Rc<RefCell<Node>>) -> String {
+    let mut result = String::new();
+    if let NodeKind::Text(ref s) = node.borrow().kind {
+        result.push_str(s);
+    }
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        result.push_str(&text_content(&c));
+        child = c.borrow().next_sibling();
+    }
+    result
+}
+
+/// https://html.spec.whatwg.org/multipage/semantics.html#update-a-style-block
+/// `root`以下の全ての<style>要素の中身を文書順に返す
+pub fn collect_styles(root: &Rc<RefCell<Node>>) -> Vec<String> {
+    let mut styles = Vec::new();
+    collect_style_elements(root, &mut styles);
+    styles
+}
+
+fn collect_style_elements(node: &Rc<RefCell<Node>>, result: &mut Vec<String>) {
+    if node.borrow().element_kind() == Some(ElementKind::Style) {
+        result.push(text_content(node));
+        return;
+    }
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        collect_style_elements(&c, result);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// 全ての<style>要素の中身を文書順に1つのスタイルシートにまとめる。後の<style>の規則ほど
+/// 後ろに並ぶので、カスケードで詳細度が同じ規則は後の<style>のものが優先される
+pub fn merge_styles(root: &Rc<RefCell<Node>>) -> String {
+    collect_styles(root).join("\n")
+}
+
+/// URLを値に持つ属性(a[href]、img[src]、link[href]、script[src])を文書順に全て訪れ、
+/// `rewrite`が返した値で書き換える。プロキシを通したり、全てのリンクを絶対URLに
+/// 変換したりするために使う。書き換えはWindowを通して行うので、MutationObserverにも通知される
+pub fn rewrite_urls<F>(window: &Rc<RefCell<Window>>, mut rewrite: F)
+where
+    F: FnMut(ElementKind, &str) -> String,
+{
+    let mut targets = Vec::new();
+    collect_url_attributes(&window.borrow().document(), &mut targets);
+
+    for (node, kind, name, value) in targets {
+        let rewritten = rewrite(kind, &value);
+        if rewritten != value {
+            window.borrow_mut().set_attribute(&node, name, &rewritten);
+        }
+    }
+}
+
+fn collect_url_attributes(
+    node: &Rc<RefCell<Node>>,
+    result: &mut Vec<(Rc<RefCell<Node>>, ElementKind, &'static str, String)>,
+) {
+    if let Some(element) = node.borrow().get_element() {
+        let name = match element.kind() {
+            ElementKind::A | ElementKind::Link => Some("href"),
+            ElementKind::Img | ElementKind::Script => Some("src"),
+            _ => None,
+        };
+        if let Some(name) = name {
+            if let Some(value) = element.get_attribute(name) {
+                result.push((node.clone(), element.kind(), name, value));
+            }
+        }
+    }
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        collect_url_attributes(&c, result);
+        child = c.borrow().next_sibling();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::String;
+    use crate::alloc::string::ToString;
+    use crate::renderer::dom::node::Element;
+    use crate::renderer::dom::serializer::serialize;
+    use crate::renderer::dom::serializer::SerializeOptions;
+    use crate::renderer::html::attribute::Attribute;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use crate::url::Url;
+    use alloc::vec;
+
+    fn id_attribute(id: &str) -> Attribute {
+        let mut attr = Attribute::new();
+        "id".chars().for_each(|c| attr.add_char(c, true));
+        id.chars().for_each(|c| attr.add_char(c, false));
+        attr
+    }
+
+    #[test]
+    fn test_get_element_by_id_index() {
+        let html = "<html><head></head><body><p id=\"a\">x</p><p id=\"b\">y</p><p id=\"a\">z</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let document = window.borrow().document();
+
+        for id in ["a", "b"] {
+            let linear = get_element_by_id(Some(document.clone()), id)
+                .expect("failed to find an element by linear scan");
+            let indexed = window
+                .borrow_mut()
+                .get_element_by_id(id)
+                .expect("failed to find an element by index");
+            assert!(Rc::ptr_eq(&linear, &indexed));
+        }
+
+        assert!(window.borrow_mut().get_element_by_id("c").is_none());
+    }
+
+    #[test]
+    fn test_get_element_by_id_after_append_child() {
+        let html = "<html><head></head><body><p id=\"a\">x</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        assert!(window.borrow_mut().get_element_by_id("new").is_none());
+
+        let body = get_element_by_id(Some(window.borrow().document()), "a")
+            .expect("failed to find an element")
+            .borrow()
+            .parent()
+            .upgrade()
+            .expect("failed to get a parent");
+        let child = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            "p",
+            vec![id_attribute("new")],
+        )))));
+        window.borrow_mut().append_child(&body, child.clone());
+
+        let found = window
+            .borrow_mut()
+            .get_element_by_id("new")
+            .expect("failed to find an appended element");
+        assert!(Rc::ptr_eq(&child, &found));
+    }
+
+    #[test]
+    fn test_append_child_moves_node() {
+        let html = "<html><head></head><body><p>a</p><p>b</p></body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construction_tree();
+        let body = window.borrow().body().expect("failed to get body");
+        let first = body.borrow().first_child().expect("failed to get a child");
+        let second = first.borrow().next_sibling().expect("failed to get a child");
+
+        // 同じ親の最後に移す
+        append_child(&body, first.clone());
+        assert_eq!(
+            "<body><p>b</p><p>a</p></body>",
+            serialize(&body, &SerializeOptions::default())
+        );
+        assert!(Rc::ptr_eq(
+            &second,
+            &body.borrow().first_child().expect("failed to get a child")
+        ));
+        assert!(second.borrow().precious_sibling().upgrade().is_none());
+        assert!(first.borrow().next_sibling().is_none());
+
+        // 別の親に移す
+        append_child(&second, first.clone());
+        assert_eq!(
+            "<body><p>b<p>a</p></p></body>",
+            serialize(&body, &SerializeOptions::default())
+        );
+        assert!(Rc::ptr_eq(
+            &second,
+            &body.borrow().last_child().upgrade().expect("failed to get a child")
+        ));
+        assert!(second.borrow().next_sibling().is_none());
+        assert!(Rc::ptr_eq(
+            &second,
+            &first.borrow().parent().upgrade().expect("failed to get a parent")
+        ));
+    }
+
+    #[test]
+    fn test_append_child_rejects_ancestor() {
+        let html = "<html><head></head><body><p>a</p></body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construction_tree();
+        let body = window.borrow().body().expect("failed to get body");
+        let p = body.borrow().first_child().expect("failed to get a child");
+        let expected = "<body><p>a</p></body>";
+
+        assert!(!append_child(&p, body.clone()));
+        assert!(!append_child(&body, body.clone()));
+        assert!(!window.borrow_mut().append_child(&p, body.clone()));
+        assert_eq!(expected, serialize(&body, &SerializeOptions::default()));
+        assert!(Rc::ptr_eq(
+            &body,
+            &p.borrow().parent().upgrade().expect("failed to get a parent")
+        ));
+        let html = body.borrow().parent().upgrade().expect("failed to get a parent");
+        assert_eq!(Some(ElementKind::Html), html.borrow().element_kind());
+    }
+
+    #[test]
+    fn test_remove_child() {
+        let html = "<html><head></head><body><p>a</p><p>b</p><p>c</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let document = window.borrow().document();
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get html")
+            .borrow()
+            .last_child()
+            .upgrade()
+            .expect("failed to get body");
+
+        let first = body.borrow().first_child().expect("failed to get a child");
+        let second = first.borrow().next_sibling().expect("failed to get a child");
+        let third = second.borrow().next_sibling().expect("failed to get a child");
+
+        assert!(remove_child(&body, &second));
+        assert!(!remove_child(&body, &second));
+        assert!(Rc::ptr_eq(
+            &third,
+            &first.borrow().next_sibling().expect("failed to get a sibling")
+        ));
+        assert!(Rc::ptr_eq(
+            &first,
+            &third
+                .borrow()
+                .precious_sibling()
+                .upgrade()
+                .expect("failed to get a sibling")
+        ));
+
+        assert!(remove_child(&body, &third));
+        assert!(first.borrow().next_sibling().is_none());
+        assert!(Rc::ptr_eq(
+            &first,
+            &body.borrow().last_child().upgrade().expect("failed to get a child")
+        ));
+    }
+
+    #[test]
+    fn test_focusable_elements() {
+        let html = "<html><head></head><body>\
+            <a id=\"1\" href=\"a\">a</a>\
+            <a id=\"2\">no href</a>\
+            <p id=\"3\" tabindex=\"2\">p</p>\
+            <a id=\"4\" href=\"b\" tabindex=\"-1\">b</a>\
+            <select id=\"5\"></select>\
+            <a id=\"6\" href=\"c\" tabindex=\"1\">c</a>\
+            <a id=\"7\" href=\"d\" tabindex=\"x\">d</a>\
+            <input id=\"8\" type=\"Hidden\">\
+            <input id=\"9\" disabled>\
+            <select id=\"10\" disabled tabindex=\"3\"></select>\
+            <input id=\"11\" type=\"hidden\" tabindex=\"1\">\
+            <input id=\"12\" type=\"text\">\
+            </body></html>"
+            .to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let document = window.borrow().document();
+
+        let ids: Vec<_> = focusable_elements(&document)
+            .iter()
+            .map(|n| {
+                n.borrow()
+                    .get_element()
+                    .and_then(|e| e.get_attribute("id"))
+                    .expect("failed to get id")
+            })
+            .collect();
+        assert_eq!(vec!["6", "3", "1", "5", "7", "12"], ids);
+    }
+
+    #[test]
+    fn test_form_controls() {
+        let html = "<html><head></head><body>\
+            <input name=\"outside\">\
+            <form id=\"f\">\
+            <input name=\"q\">\
+            <p><select name=\"s\"><option>a</select></p>\
+            <textarea name=\"t\">text</textarea>\
+            </form>\
+            </body></html>"
+            .to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let form = get_element_by_id(Some(window.borrow().document()), "f")
+            .expect("failed to find a form");
+        assert_eq!(Some(ElementKind::Form), form.borrow().element_kind());
+
+        let names: Vec<_> = form_controls(&form)
+            .iter()
+            .map(|n| {
+                n.borrow()
+                    .get_element()
+                    .and_then(|e| e.get_attribute("name"))
+                    .expect("failed to get name")
+            })
+            .collect();
+        assert_eq!(vec!["q", "s", "t"], names);
+    }
+
+    fn value(node: &Rc<RefCell<Node>>) -> String {
+        node.borrow().get_element().expect("failed to get an element").value()
+    }
+
+    #[test]
+    fn test_insert_text_and_backspace() {
+        let html = "<html><head></head><body><input id=\"i\" value=\"ab\"></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let input = get_element_by_id(Some(window.borrow().document()), "i")
+            .expect("failed to find an input");
+
+        // カーソルは最初は値の末尾にある
+        assert!(insert_text(&input, 'c'));
+        assert!(insert_text(&input, 'd'));
+        assert_eq!("abcd", value(&input));
+
+        assert!(backspace(&input));
+        assert_eq!("abc", value(&input));
+
+        if let NodeKind::Element(ref mut e) = input.borrow_mut().kind {
+            e.set_cursor(1);
+        }
+        assert!(insert_text(&input, 'あ'));
+        assert_eq!("aあbc", value(&input));
+        assert!(backspace(&input));
+        assert!(backspace(&input));
+        assert_eq!("bc", value(&input));
+        // 先頭では何も削除されない
+        assert!(!backspace(&input));
+        assert_eq!("bc", value(&input));
+    }
+
+    #[test]
+    fn test_insert_text_into_non_text_input() {
+        let html = "<html><head></head><body><input id=\"c\" type=\"checkbox\"><p id=\"p\">x</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let document = window.borrow().document();
+        for id in ["c", "p"] {
+            let node = get_element_by_id(Some(document.clone()), id).expect("failed to find a node");
+            assert!(!insert_text(&node, 'a'));
+            assert!(!backspace(&node));
+        }
+    }
+
+    #[test]
+    fn test_find_all() {
+        let html = "<html><head></head><body><p>Saba is a browser. SABA!</p><p>sabasaba</p><p>none</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let document = window.borrow().document();
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get html")
+            .borrow()
+            .last_child()
+            .upgrade()
+            .expect("failed to get body");
+        let first_p = body.borrow().first_child().expect("failed to get a child");
+        let second_p = first_p.borrow().next_sibling().expect("failed to get a child");
+        let first = first_p.borrow().first_child().expect("failed to get a text");
+        let second = second_p.borrow().first_child().expect("failed to get a text");
+
+        assert_eq!(
+            vec![
+                (first.borrow().id(), 0),
+                (first.borrow().id(), 19),
+                (second.borrow().id(), 0),
+                (second.borrow().id(), 4),
+            ],
+            find_all(&document, "saba")
+        );
+        // 重なり合う一致は最初のものだけを返す
+        assert_eq!(vec![(second.borrow().id(), 1)], find_all(&document, "abasa"));
+        assert!(find_all(&document, "missing").is_empty());
+        assert!(find_all(&document, "").is_empty());
+    }
+
+    #[test]
+    fn test_rewrite_urls() {
+        let html = "<html><head><link rel=stylesheet href=style.css><script src=app.js></script></head><body><a href=page.html>a</a><a>no href</a><img src=/img.png><a href=http://other.com/>b</a></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let base = Url::new("http://example.com/dir/".to_string())
+            .parse()
+            .expect("failed to parse url");
+        window.borrow_mut().set_base_url(base);
+
+        let mut visited = Vec::new();
+        rewrite_urls(&window, |kind, value| {
+            visited.push(kind);
+            let url = window.borrow().resolve_url(value).expect("failed to resolve url");
+            url.href()
+        });
+        assert_eq!(
+            vec![
+                ElementKind::Link,
+                ElementKind::Script,
+                ElementKind::A,
+                ElementKind::Img,
+                ElementKind::A,
+            ],
+            visited
+        );
+
+        let document = window.borrow().document();
+        let attribute = |kind: ElementKind, name: &str| -> Vec<String> {
+            let mut values = Vec::new();
+            let mut targets = Vec::new();
+            collect_url_attributes(&document, &mut targets);
+            for (_, k, n, v) in targets {
+                if k == kind && n == name {
+                    values.push(v);
+                }
+            }
+            values
+        };
+        assert_eq!(
+            vec!["http://example.com/dir/style.css"],
+            attribute(ElementKind::Link, "href")
+        );
+        assert_eq!(
+            vec!["http://example.com/dir/app.js"],
+            attribute(ElementKind::Script, "src")
+        );
+        assert_eq!(
+            vec!["http://example.com/img.png"],
+            attribute(ElementKind::Img, "src")
+        );
+        assert_eq!(
+            vec!["http://example.com/dir/page.html", "http://other.com/"],
+            attribute(ElementKind::A, "href")
+        );
+    }
+
+    #[test]
+    fn test_collect_styles() {
+        let html = "<html><head><style>p { color: red; }</style></head><body><p>a</p><style>p { color: blue; }</style></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let document = window.borrow().document();
+
+        assert_eq!(
+            vec!["p { color: red; }", "p { color: blue; }"],
+            collect_styles(&document)
+        );
+        // 後の<style>の規則が後ろに並ぶ
+        assert_eq!(
+            "p { color: red; }\np { color: blue; }",
+            merge_styles(&document)
+        );
+
+        let html = "<html><head></head><body><p>a</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        assert!(collect_styles(&window.borrow().document()).is_empty());
+    }
+}
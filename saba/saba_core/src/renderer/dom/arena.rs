@@ -0,0 +1,285 @@
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeId;
+use crate::renderer::dom::node::NodeKind;
+use crate::renderer::dom::serializer::dump_kind;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+#[derive(Debug, Clone)]
+pub struct ArenaNode {
+    kind: NodeKind,
+    id: NodeId,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+}
+
+impl ArenaNode {
+    fn new(kind: NodeKind, id: NodeId) -> Self {
+        Self {
+            kind,
+            id,
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+        }
+    }
+
+    pub fn kind(&self) -> &NodeKind {
+        &self.kind
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn first_child(&self) -> Option<NodeId> {
+        self.first_child
+    }
+
+    pub fn last_child(&self) -> Option<NodeId> {
+        self.last_child
+    }
+
+    pub fn previous_sibling(&self) -> Option<NodeId> {
+        self.previous_sibling
+    }
+
+    pub fn next_sibling(&self) -> Option<NodeId> {
+        self.next_sibling
+    }
+}
+
+/// `Rc<RefCell<Node>>`の代わりに、ノードを1つの`Vec`に並べて持つDOM。親・子・兄弟への
+/// 参照はNodeIdで表すので、参照カウントの増減や循環参照が起きない。NodeIdは
+/// Rcのツリーのノードと同じ番号空間から割り当てるため、どちらのツリーでも重ならない。
+///
+/// パーサはRcのツリーを直接組み立てるので、パースした文書をArenaDomで持つには
+/// from_treeで写す必要があり、その分はRcのツリーだけを使うより費用がかかる。
+/// ArenaDomを直接組み立てられるのは、今はappend_childを使う場合だけ
+#[derive(Debug, Clone)]
+pub struct ArenaDom {
+    nodes: Vec<ArenaNode>,
+    /// NodeIdから`nodes`の中の位置を引く表
+    positions: BTreeMap<NodeId, usize>,
+}
+
+impl Default for ArenaDom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArenaDom {
+    /// Documentノードだけを持つArenaDomを作る
+    pub fn new() -> Self {
+        let document = ArenaNode::new(NodeKind::Document, NodeId::next());
+        let mut positions = BTreeMap::new();
+        positions.insert(document.id, 0);
+        Self {
+            nodes: vec![document],
+            positions,
+        }
+    }
+
+    /// `Rc`で作られたツリーを、同じ構造とNodeIdのArenaDomに写す。`root`がDocumentでない
+    /// 場合は、新しいDocumentの子として写す
+    pub fn from_tree(root: &Rc<RefCell<Node>>) -> Self {
+        let mut dom = Self::new();
+        if let NodeKind::Document = root.borrow().kind {
+            let document = ArenaNode::new(NodeKind::Document, root.borrow().id());
+            dom.positions.clear();
+            dom.positions.insert(document.id, 0);
+            dom.nodes[0] = document;
+            let document = dom.document();
+            dom.copy_children(document, root);
+        } else {
+            let document = dom.document();
+            dom.copy_node(document, root);
+        }
+        dom
+    }
+
+    fn copy_node(&mut self, parent: NodeId, node: &Rc<RefCell<Node>>) {
+        let (kind, id) = {
+            let n = node.borrow();
+            (n.kind.clone(), n.id())
+        };
+        self.push_child(parent, kind, id);
+        self.copy_children(id, node);
+    }
+
+    fn copy_children(&mut self, parent: NodeId, node: &Rc<RefCell<Node>>) {
+        let mut child = node.borrow().first_child();
+        while let Some(c) = child {
+            self.copy_node(parent, &c);
+            child = c.borrow().next_sibling();
+        }
+    }
+
+    pub fn document(&self) -> NodeId {
+        self.nodes[0].id
+    }
+
+    /// Documentノードを除いたノードの数
+    pub fn len(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// Documentノードしかない場合はtrue
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&ArenaNode> {
+        self.positions.get(&id).map(|&i| &self.nodes[i])
+    }
+
+    fn get_mut(&mut self, id: NodeId) -> &mut ArenaNode {
+        let i = self.positions[&id];
+        &mut self.nodes[i]
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-node-append
+    /// `kind`のノードを作り、`parent`の最後の子として追加する
+    pub fn append_child(&mut self, parent: NodeId, kind: NodeKind) -> NodeId {
+        let id = NodeId::next();
+        self.push_child(parent, kind, id);
+        id
+    }
+
+    fn push_child(&mut self, parent: NodeId, kind: NodeKind, id: NodeId) {
+        assert!(self.positions.contains_key(&parent), "parent is not in the arena");
+
+        let last_child = self.get_mut(parent).last_child;
+        let mut node = ArenaNode::new(kind, id);
+        node.parent = Some(parent);
+        node.previous_sibling = last_child;
+        self.positions.insert(id, self.nodes.len());
+        self.nodes.push(node);
+
+        match last_child {
+            Some(last) => self.get_mut(last).next_sibling = Some(id),
+            None => self.get_mut(parent).first_child = Some(id),
+        }
+        self.get_mut(parent).last_child = Some(id);
+    }
+
+    pub fn children(&self, id: NodeId) -> Vec<NodeId> {
+        let mut children = Vec::new();
+        let mut child = self.get(id).and_then(|n| n.first_child);
+        while let Some(c) = child {
+            children.push(c);
+            child = self.get(c).and_then(|n| n.next_sibling);
+        }
+        children
+    }
+
+    /// dump_treeと同じ書式でツリーを出力する。Documentノード自体は出力しない
+    pub fn dump(&self) -> String {
+        let mut lines = Vec::new();
+        for child in self.children(self.document()) {
+            self.dump_node(child, 0, &mut lines);
+        }
+        lines.join("\n")
+    }
+
+    fn dump_node(&self, id: NodeId, depth: usize, lines: &mut Vec<String>) {
+        if let Some(node) = self.get(id) {
+            dump_kind(&node.kind, depth, lines);
+        }
+        for child in self.children(id) {
+            self.dump_node(child, depth + 1, lines);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::node::Element;
+    use crate::renderer::dom::serializer::dump_tree;
+    use crate::renderer::html::attribute::Attribute;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use alloc::string::ToString;
+
+    fn element(name: &str, attributes: &[(&str, &str)]) -> NodeKind {
+        let attributes = attributes
+            .iter()
+            .map(|(n, v)| Attribute::from_name_and_value(n.to_string(), v.to_string()))
+            .collect();
+        NodeKind::Element(Element::new(name, attributes))
+    }
+
+    #[test]
+    fn test_same_structure_as_parsed_tree() {
+        let html = "<html><head><style>p {}</style></head><body>\
+            <p class=\"a\" id=x>one<a href=\"/\">two</a></p><!-- c --><p>three</p></body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construction_tree();
+        let document = window.borrow().document();
+
+        // パーサとは別に、同じ文書をArenaDomに直接組み立てる
+        let mut dom = ArenaDom::new();
+        let html = dom.append_child(dom.document(), element("html", &[]));
+        let head = dom.append_child(html, element("head", &[]));
+        let style = dom.append_child(head, element("style", &[]));
+        dom.append_child(style, NodeKind::Text("p {}".to_string()));
+        let body = dom.append_child(html, element("body", &[]));
+        let p = dom.append_child(body, element("p", &[("class", "a"), ("id", "x")]));
+        dom.append_child(p, NodeKind::Text("one".to_string()));
+        let a = dom.append_child(p, element("a", &[("href", "/")]));
+        dom.append_child(a, NodeKind::Text("two".to_string()));
+        dom.append_child(body, NodeKind::Comment(" c ".to_string()));
+        let p = dom.append_child(body, element("p", &[]));
+        dom.append_child(p, NodeKind::Text("three".to_string()));
+
+        assert_eq!(dump_tree(&document), dom.dump());
+
+        // 写したArenaDomは同じ構造で、ノードのNodeIdも元のツリーと同じ
+        let copied = ArenaDom::from_tree(&document);
+        assert_eq!(dom.dump(), copied.dump());
+        assert_eq!(dom.len(), copied.len());
+        let body_node = window.borrow().body().expect("failed to find body");
+        let copied_body = copied
+            .get(body_node.borrow().id())
+            .expect("failed to find body in the arena");
+        assert_eq!(3, copied.children(copied_body.id()).len());
+    }
+
+    #[test]
+    fn test_append_child() {
+        let mut dom = ArenaDom::new();
+        assert!(dom.is_empty());
+        assert_eq!(0, dom.len());
+
+        let html = dom.append_child(dom.document(), element("html", &[]));
+        let body = dom.append_child(html, element("body", &[]));
+        let p1 = dom.append_child(body, element("p", &[]));
+        let p2 = dom.append_child(body, element("p", &[]));
+        dom.append_child(p2, NodeKind::Text("a".to_string()));
+
+        assert_eq!(5, dom.len());
+        assert!(!dom.is_empty());
+        assert_eq!(vec![p1, p2], dom.children(body));
+        let p2_node = dom.get(p2).expect("failed to get a node");
+        assert_eq!(Some(body), p2_node.parent());
+        assert_eq!(Some(p1), p2_node.previous_sibling());
+        assert_eq!(None, p2_node.next_sibling());
+        assert_eq!(Some(p2), dom.get(p1).and_then(|n| n.next_sibling()));
+        assert_eq!(Some(p2), dom.get(body).and_then(|n| n.last_child()));
+        assert_eq!("<html>\n  <body>\n    <p>\n    <p>\n      \"a\"", dom.dump());
+    }
+}
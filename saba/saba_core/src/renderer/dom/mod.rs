@@ -1 +1,6 @@
-pub mod node;
\ No newline at end of file
+pub mod api;
+pub mod arena;
+pub mod mutation;
+pub mod node;
+pub mod selection;
+pub mod serializer;
\ No newline at end of file
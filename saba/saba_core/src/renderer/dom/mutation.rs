@@ -0,0 +1,216 @@
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::Window;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::fmt;
+
+/// https://dom.spec.whatwg.org/#dom-mutationrecord-type
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MutationKind {
+    /// 子ノードが追加された
+    ChildAdded,
+    /// 子ノードが取り除かれた
+    ChildRemoved,
+    /// 属性が変更された
+    Attributes,
+}
+
+/// https://dom.spec.whatwg.org/#interface-mutationrecord
+#[derive(Debug, Clone)]
+pub struct MutationRecord {
+    kind: MutationKind,
+    /// 追加・削除されたノード、または属性が変更された要素
+    target: Rc<RefCell<Node>>,
+}
+
+impl MutationRecord {
+    pub fn new(kind: MutationKind, target: Rc<RefCell<Node>>) -> Self {
+        Self { kind, target }
+    }
+
+    pub fn kind(&self) -> MutationKind {
+        self.kind
+    }
+
+    pub fn target(&self) -> Rc<RefCell<Node>> {
+        self.target.clone()
+    }
+}
+
+/// https://dom.spec.whatwg.org/#interface-mutationobserver
+#[derive(Clone)]
+pub struct MutationObserver {
+    callback: Rc<dyn Fn(&MutationRecord)>,
+}
+
+impl MutationObserver {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&MutationRecord) + 'static,
+    {
+        Self {
+            callback: Rc::new(callback),
+        }
+    }
+}
+
+impl fmt::Debug for MutationObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MutationObserver")
+    }
+}
+
+/// https://dom.spec.whatwg.org/#notify-mutation-observers
+/// 溜まっている変更の記録を監視者に通知する。Windowの借用を解放してから
+/// コールバックを呼び出すため、コールバックの中からDOMを変更してもツリーは壊れない。
+/// コールバックの中で行われた変更は、次にこの関数を呼んだときに通知される
+pub fn notify_mutation_observers(window: &Rc<RefCell<Window>>) {
+    let (records, observers) = window.borrow_mut().take_mutation_records();
+
+    for record in &records {
+        for observer in &observers {
+            (observer.callback)(record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use crate::renderer::dom::api::get_element_by_id;
+    use crate::renderer::dom::node::Element;
+    use crate::renderer::dom::node::NodeKind;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use alloc::vec::Vec;
+
+    fn setup() -> (Rc<RefCell<Window>>, Rc<RefCell<Vec<MutationRecord>>>) {
+        let html = "<html><head></head><body><p id=\"a\">x</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let r = records.clone();
+        window
+            .borrow_mut()
+            .add_mutation_observer(MutationObserver::new(move |record| {
+                r.borrow_mut().push(record.clone());
+            }));
+
+        (window, records)
+    }
+
+    #[test]
+    fn test_observe_child_list() {
+        let (window, records) = setup();
+        let p = get_element_by_id(Some(window.borrow().document()), "a")
+            .expect("failed to find an element");
+        let body = p.borrow().parent().upgrade().expect("failed to get body");
+
+        let child = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            "p",
+            Vec::new(),
+        )))));
+        window.borrow_mut().append_child(&body, child.clone());
+        window.borrow_mut().remove_child(&body, &p);
+        // 通知されるまでは記録が溜まるだけ
+        assert!(records.borrow().is_empty());
+
+        notify_mutation_observers(&window);
+        let records = records.borrow();
+        assert_eq!(2, records.len());
+        assert_eq!(MutationKind::ChildAdded, records[0].kind());
+        assert!(Rc::ptr_eq(&child, &records[0].target()));
+        assert_eq!(MutationKind::ChildRemoved, records[1].kind());
+        assert!(Rc::ptr_eq(&p, &records[1].target()));
+    }
+
+    #[test]
+    fn test_observe_attributes() {
+        let (window, records) = setup();
+        let p = get_element_by_id(Some(window.borrow().document()), "a")
+            .expect("failed to find an element");
+
+        window.borrow_mut().set_attribute(&p, "class", "c");
+        notify_mutation_observers(&window);
+
+        let records = records.borrow();
+        assert_eq!(1, records.len());
+        assert_eq!(MutationKind::Attributes, records[0].kind());
+        assert!(Rc::ptr_eq(&p, &records[0].target()));
+    }
+
+    #[test]
+    fn test_observe_class_list() {
+        let (window, records) = setup();
+        let p = get_element_by_id(Some(window.borrow().document()), "a")
+            .expect("failed to find an element");
+        let class = |p: &Rc<RefCell<Node>>| p.borrow().get_element().unwrap().get_attribute("class");
+
+        // class属性がない要素から取り除いても、属性は作られず通知もされない
+        window.borrow_mut().remove_class(&p, "x");
+        assert_eq!(None, class(&p));
+
+        window.borrow_mut().add_class(&p, "x");
+        window.borrow_mut().add_class(&p, "y");
+        assert!(!window.borrow_mut().toggle_class(&p, "x"));
+        assert_eq!(Some("y".to_string()), class(&p));
+        window.borrow_mut().remove_class(&p, "y");
+        assert_eq!(Some("".to_string()), class(&p));
+
+        notify_mutation_observers(&window);
+        let records = records.borrow();
+        assert_eq!(4, records.len());
+        for record in records.iter() {
+            assert_eq!(MutationKind::Attributes, record.kind());
+            assert!(Rc::ptr_eq(&p, &record.target()));
+        }
+    }
+
+    #[test]
+    fn test_mutation_in_callback() {
+        let html = "<html><head></head><body></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let body = window
+            .borrow()
+            .document()
+            .borrow()
+            .first_child()
+            .expect("failed to get html")
+            .borrow()
+            .last_child()
+            .upgrade()
+            .expect("failed to get body");
+
+        let count = Rc::new(RefCell::new(0));
+        let c = count.clone();
+        let w = Rc::downgrade(&window);
+        window
+            .borrow_mut()
+            .add_mutation_observer(MutationObserver::new(move |record| {
+                *c.borrow_mut() += 1;
+                // コールバックの中からDOMを変更しても、借用の衝突は起きない
+                if let Some(window) = w.upgrade() {
+                    window
+                        .borrow_mut()
+                        .set_attribute(&record.target(), "seen", "");
+                }
+            }));
+
+        let child = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            "p",
+            Vec::new(),
+        )))));
+        window.borrow_mut().append_child(&body, child.clone());
+        notify_mutation_observers(&window);
+        assert_eq!(1, *count.borrow());
+
+        // コールバックの中での属性の変更は、次の通知で届く
+        notify_mutation_observers(&window);
+        assert_eq!(2, *count.borrow());
+        assert_eq!(
+            Some("".to_string()),
+            child.borrow().get_element().unwrap().get_attribute("seen")
+        );
+    }
+}
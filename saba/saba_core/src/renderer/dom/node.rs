@@ -1,27 +1,61 @@
+use alloc::collections::BTreeMap;
 use alloc::format;
 use alloc::rc::Rc;
 use alloc::rc::Weak;
 use alloc::string::String;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use core::cell::RefCell;
-use crate::renderer::html::attribute;
+use core::str::FromStr;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+use crate::renderer::dom::api::append_child;
+use crate::renderer::dom::api::remove_child;
+use crate::renderer::dom::mutation::MutationKind;
+use crate::renderer::dom::mutation::MutationObserver;
+use crate::renderer::dom::mutation::MutationRecord;
 use crate::renderer::html::attribute::Attribute;
+use crate::url::Url;
 
 // https://html.spec.whatwg.org/multipage/nav-history-apis.html#window
 #[derive(Debug, Clone)]
 pub struct Window {
     document: Rc<RefCell<Node>>,
+    /// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#document-base-url
+    base_url: Option<Url>,
+    /// https://html.spec.whatwg.org/multipage/links.html#rel-icon
+    /// 最後に宣言されたアイコンのリンク先
+    icon_url: Option<Url>,
+    /// https://html.spec.whatwg.org/multipage/webappapis.html#concept-n-script
+    /// 本書のブラウザはJavaScriptを実行しないため、既定では無効にする
+    scripting_enabled: bool,
+    /// idから要素を引くための索引。DOMが変更されるとNoneに戻し、次の検索時に作り直す
+    id_index: Option<BTreeMap<String, Weak<RefCell<Node>>>>,
+    /// NodeIdからノードを引くための表。Window::append_childやパーサがノードを挿入するときに登録する
+    node_registry: BTreeMap<NodeId, Weak<RefCell<Node>>>,
+    mutation_observers: Vec<MutationObserver>,
+    /// まだ監視者に通知していないDOMの変更の記録
+    mutation_records: Vec<MutationRecord>,
 }
 
 impl Window {
     pub fn new() -> Self {
-        let window = Self {
-            document: Rc::new(RefCell::new(Node::new(NodeKind::Document)))
+        let mut window = Self {
+            document: Rc::new(RefCell::new(Node::new(NodeKind::Document))),
+            base_url: None,
+            icon_url: None,
+            scripting_enabled: false,
+            id_index: None,
+            node_registry: BTreeMap::new(),
+            mutation_observers: Vec::new(),
+            mutation_records: Vec::new(),
         };
+        let document = window.document.clone();
+        window.register_node(&document);
 
         window
             .document
-            .borrow_mut
+            .borrow_mut()
             .set_window(Rc::downgrade(&Rc::new(RefCell::new(window.clone()))));
         
         window
@@ -30,13 +64,257 @@ impl Window {
     pub fn document(&self) -> Rc<RefCell<Node>> {
         self.document.clone()
     }
+
+    /// https://dom.spec.whatwg.org/#dom-document-documentelement
+    /// 文書の最初の子要素(通常は<html>)を返す。文書の前にあるコメントは飛ばす
+    pub fn document_element(&self) -> Option<Rc<RefCell<Node>>> {
+        first_child_element(&self.document, |_| true)
+    }
+
+    /// https://html.spec.whatwg.org/multipage/dom.html#dom-document-body
+    /// <html>の子の最初の<body>を返す。<body>が省略された文書でも、パーサが補った<body>を返す
+    pub fn body(&self) -> Option<Rc<RefCell<Node>>> {
+        let html = self.document_element()?;
+        if html.borrow().element_kind() != Some(ElementKind::Html) {
+            return None;
+        }
+        first_child_element(&html, |kind| kind == ElementKind::Body)
+    }
+
+    pub fn base_url(&self) -> Option<Url> {
+        self.base_url.clone()
+    }
+
+    pub fn set_base_url(&mut self, base_url: Url) {
+        self.base_url = Some(base_url);
+    }
+
+    pub fn icon_url(&self) -> Option<Url> {
+        self.icon_url.clone()
+    }
+
+    pub fn set_icon_url(&mut self, icon_url: Url) {
+        self.icon_url = Some(icon_url);
+    }
+
+    pub fn scripting_enabled(&self) -> bool {
+        self.scripting_enabled
+    }
+
+    pub fn set_scripting_enabled(&mut self, scripting_enabled: bool) {
+        self.scripting_enabled = scripting_enabled;
+    }
+
+    /// リンクやリソースの参照先を、文書の基準URLを使って絶対URLに解決する
+    pub fn resolve_url(&self, input: &str) -> Result<Url, String> {
+        match self.base_url {
+            Some(ref base_url) => base_url.join(input),
+            None => Url::new(input.to_string()).parse(),
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-nonelementparentnode-getelementbyid
+    /// 同じidを持つ要素が複数ある場合は、文書順で最初の要素を返す
+    pub fn get_element_by_id(&mut self, id: &str) -> Option<Rc<RefCell<Node>>> {
+        if self.id_index.is_none() {
+            let mut index = BTreeMap::new();
+            build_id_index(&self.document, &mut index);
+            self.id_index = Some(index);
+        }
+
+        self.id_index
+            .as_ref()
+            .and_then(|index| index.get(id))
+            .and_then(|node| node.upgrade())
+    }
+
+    /// 登録されたノードを`id`から引く。文書から取り除かれたノードも、破棄されるまでは引ける。
+    /// 登録されていないidや、破棄されたノードのidの場合はNoneを返す
+    pub fn get_node(&mut self, id: NodeId) -> Option<Rc<RefCell<Node>>> {
+        let node = self.node_registry.get(&id).and_then(|n| n.upgrade());
+        if node.is_none() {
+            // 破棄されたノードの項目は、次からの検索のために取り除く
+            self.node_registry.remove(&id);
+        }
+        node
+    }
+
+    /// `node`とその子孫をget_nodeで引けるように登録する。ツリーにノードを挿入したときに呼び出す
+    pub fn register_node(&mut self, node: &Rc<RefCell<Node>>) {
+        build_node_registry(node, &mut self.node_registry);
+    }
+
+    /// DOMが変更されたときに呼び出し、idの索引を破棄する
+    pub fn invalidate_id_index(&mut self) {
+        self.id_index = None;
+    }
+
+    /// `child`が`parent`自身かその祖先であれば何もせずにfalseを返す
+    pub fn append_child(&mut self, parent: &Rc<RefCell<Node>>, child: Rc<RefCell<Node>>) -> bool {
+        if !append_child(parent, child.clone()) {
+            return false;
+        }
+        self.register_node(&child);
+        self.invalidate_id_index();
+        self.queue_mutation_record(MutationKind::ChildAdded, child);
+        true
+    }
+
+    /// `child`が`parent`の子でなければ何もせずにfalseを返す
+    pub fn remove_child(&mut self, parent: &Rc<RefCell<Node>>, child: &Rc<RefCell<Node>>) -> bool {
+        if !remove_child(parent, child) {
+            return false;
+        }
+        self.invalidate_id_index();
+        self.queue_mutation_record(MutationKind::ChildRemoved, child.clone());
+        true
+    }
+
+    /// `node`が要素でなければ何もしない
+    pub fn set_attribute(&mut self, node: &Rc<RefCell<Node>>, name: &str, value: &str) {
+        if let NodeKind::Element(ref mut e) = node.borrow_mut().kind {
+            e.set_attribute(name, value);
+        } else {
+            return;
+        }
+        if name == "id" {
+            self.invalidate_id_index();
+        }
+        self.queue_mutation_record(MutationKind::Attributes, node.clone());
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-getattribute
+    pub fn get_attribute(&self, node: &Rc<RefCell<Node>>, name: &str) -> Option<String> {
+        match node.borrow().kind {
+            NodeKind::Element(ref e) => e.get_attribute(name),
+            _ => None,
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-removeattribute
+    /// 属性が存在しなければ何もしない
+    pub fn remove_attribute(&mut self, node: &Rc<RefCell<Node>>, name: &str) {
+        let removed = match node.borrow_mut().kind {
+            NodeKind::Element(ref mut e) => e.remove_attribute(name),
+            _ => false,
+        };
+        if !removed {
+            return;
+        }
+        if name == "id" {
+            self.invalidate_id_index();
+        }
+        self.queue_mutation_record(MutationKind::Attributes, node.clone());
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-add
+    /// ツリーの中の`node`にクラスを追加する。`node`が要素でなければ何もしない
+    pub fn add_class(&mut self, node: &Rc<RefCell<Node>>, class: &str) {
+        self.update_class_list(node, |e| e.add_class(class));
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-remove
+    pub fn remove_class(&mut self, node: &Rc<RefCell<Node>>, class: &str) {
+        self.update_class_list(node, |e| e.remove_class(class));
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-toggle
+    /// 操作後にクラスが含まれていればtrueを返す。`node`が要素でなければfalseを返す
+    pub fn toggle_class(&mut self, node: &Rc<RefCell<Node>>, class: &str) -> bool {
+        let mut present = false;
+        self.update_class_list(node, |e| present = e.toggle_class(class));
+        present
+    }
+
+    /// 要素の複製に対して`update`を行い、その結果のclass属性をset_attributeで`node`に設定する。
+    /// set_attributeを通すので、変更は監視者に通知される
+    fn update_class_list<F>(&mut self, node: &Rc<RefCell<Node>>, update: F)
+    where
+        F: FnOnce(&mut Element),
+    {
+        let mut element = match node.borrow().get_element() {
+            Some(element) => element,
+            None => return,
+        };
+        update(&mut element);
+        if let Some(class) = element.get_attribute("class") {
+            self.set_attribute(node, "class", &class);
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-mutationobserver-observe
+    pub fn add_mutation_observer(&mut self, observer: MutationObserver) {
+        self.mutation_observers.push(observer);
+    }
+
+    /// https://dom.spec.whatwg.org/#queue-a-mutation-record
+    fn queue_mutation_record(&mut self, kind: MutationKind, target: Rc<RefCell<Node>>) {
+        if self.mutation_observers.is_empty() {
+            return;
+        }
+        self.mutation_records.push(MutationRecord::new(kind, target));
+    }
+
+    /// 溜まっている変更の記録と、通知先の監視者を取り出す
+    pub fn take_mutation_records(&mut self) -> (Vec<MutationRecord>, Vec<MutationObserver>) {
+        (
+            core::mem::take(&mut self.mutation_records),
+            self.mutation_observers.clone(),
+        )
+    }
+}
+
+fn build_id_index(node: &Rc<RefCell<Node>>, index: &mut BTreeMap<String, Weak<RefCell<Node>>>) {
+    if let Some(element) = node.borrow().get_element() {
+        if let Some(id) = element.get_attribute("id") {
+            index.entry(id).or_insert_with(|| Rc::downgrade(node));
+        }
+    }
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        build_id_index(&c, index);
+        child = c.borrow().next_sibling();
+    }
+}
+
+fn build_node_registry(
+    node: &Rc<RefCell<Node>>,
+    registry: &mut BTreeMap<NodeId, Weak<RefCell<Node>>>,
+) {
+    registry.insert(node.borrow().id(), Rc::downgrade(node));
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        build_node_registry(&c, registry);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// ノードを識別するための番号。ノードの作成時に割り当てられ、以降は変わらない。
+/// `Rc`を比較したり複製したりせずに、ノードの同一性を判定できる
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub(crate) fn next() -> Self {
+        static NEXT_NODE_ID: AtomicUsize = AtomicUsize::new(0);
+        Self(NEXT_NODE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn value(&self) -> usize {
+        self.0
+    }
 }
 
+/// 子や次の兄弟への参照は`Rc`で持ち、親・最後の子・前の兄弟・Windowへの逆向きの参照は
+/// `Weak`で持つ。これにより参照が循環せず、Windowを破棄するとツリー全体が解放される
 #[derive(Debug, Clone)]
 pub struct Node {
     pub kind: NodeKind,
-    window: Weak<RefCell<window>>,
-    parent: Weak<RefCell<node>>,
+    id: NodeId,
+    window: Weak<RefCell<Window>>,
+    parent: Weak<RefCell<Node>>,
     first_child: Option<Rc<RefCell<Node>>>,
     last_child: Weak<RefCell<Node>>,
     precious_sibling: Weak<RefCell<Node>>,
@@ -47,6 +325,7 @@ impl Node {
     pub fn new(kind: NodeKind) -> Self {
         Self { 
         kind, 
+        id: NodeId::next(),
         window: Weak::new(), 
         parent: Weak::new(), 
         first_child: None, 
@@ -56,6 +335,10 @@ impl Node {
         }
     }
 
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
     pub fn set_window(&mut self, window: Weak<RefCell<Window>>) {
         self.window = window;
     }
@@ -68,7 +351,7 @@ impl Node {
         self.parent.clone()
     }
 
-    pub fn set_first_child(&mut self, first_child: Option<RefCell<Node>>) {
+    pub fn set_first_child(&mut self, first_child: Option<Rc<RefCell<Node>>>) {
         self.first_child = first_child;
     }
 
@@ -106,14 +389,14 @@ impl Node {
 
     pub fn get_element(&self) -> Option<Element> {
         match self.kind {
-            NodeKind::Document | NodeKind::Text(_) => None,
+            NodeKind::Document | NodeKind::Text(_) | NodeKind::Comment(_) => None,
             NodeKind::Element(ref e) => Some(e.clone()),
         }
     }
 
     pub fn element_kind(&self) -> Option<ElementKind> {
         match self.kind {
-            NodeKind::Document | NodeKind::Text(_) => None,
+            NodeKind::Document | NodeKind::Text(_) | NodeKind::Comment(_) => None,
             NodeKind::Element(ref e) => Some(e.kind()),
         }
     }
@@ -127,6 +410,8 @@ pub enum NodeKind {
     Element(Element),
     /// https://dom.spec.whatwg.org/#interface-text
     Text(String),
+    /// https://dom.spec.whatwg.org/#interface-comment
+    Comment(String),
 }
 
 // https://dom.spec.whatwg.org/#interface-element
@@ -134,6 +419,11 @@ pub enum NodeKind {
 pub struct Element {
     kind: ElementKind,
     attributes: Vec<Attribute>,
+    /// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-value
+    /// <input>の現在の値。値が変更されるまではNoneで、value属性の値を使う
+    value: Option<String>,
+    /// 編集中の<input>のカーソルの位置(値の先頭からの文字数)。Noneの場合は値の末尾
+    cursor: Option<usize>,
 }
 
 impl Element {
@@ -142,12 +432,157 @@ impl Element {
             kind: ElementKind::from_str(element_name)
             .expect("failed to convert string to ElementKind"),
             attributes,
+            value: None,
+            cursor: None,
         }
     }
 
     pub fn kind(&self) -> ElementKind {
         self.kind
     }
+
+    pub fn attributes(&self) -> Vec<Attribute> {
+        self.attributes.clone()
+    }
+
+    /// 同じ名前の属性が既にある場合は値を上書きし、なければ末尾に追加する
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        match self.attributes.iter_mut().find(|a| a.name() == name) {
+            Some(attr) => attr.set_value(value.to_string()),
+            None => self
+                .attributes
+                .push(Attribute::from_name_and_value(name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn get_attribute(&self, name: &str) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|a| a.name() == name)
+            .map(|a| a.value())
+    }
+
+    /// 属性を取り除いた場合はtrueを返す
+    pub fn remove_attribute(&mut self, name: &str) -> bool {
+        let len = self.attributes.len();
+        self.attributes.retain(|a| a.name() != name);
+        self.attributes.len() != len
+    }
+
+    /// https://html.spec.whatwg.org/multipage/input.html#attr-input-type
+    /// <input>のtype属性の値を小文字で返す。省略されている場合は"text"
+    pub fn input_type(&self) -> String {
+        match self.get_attribute("type") {
+            Some(t) if !t.is_empty() => t.to_ascii_lowercase(),
+            _ => "text".to_string(),
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/input.html#dom-input-value
+    /// 値が変更されていなければ、value属性の値(なければ空文字列)を返す
+    pub fn value(&self) -> String {
+        match self.value {
+            Some(ref value) => value.clone(),
+            None => self.get_attribute("value").unwrap_or_default(),
+        }
+    }
+
+    /// value属性は変更せず、現在の値のみを変更する。カーソルは値の末尾に移動する
+    pub fn set_value(&mut self, value: &str) {
+        self.value = Some(value.to_string());
+        self.cursor = None;
+    }
+
+    pub fn cursor(&self) -> usize {
+        let len = self.value().chars().count();
+        match self.cursor {
+            Some(cursor) => cursor.min(len),
+            None => len,
+        }
+    }
+
+    /// 値の長さを超える位置を指定した場合は、値の末尾に移動する
+    pub fn set_cursor(&mut self, cursor: usize) {
+        self.cursor = Some(cursor.min(self.value().chars().count()));
+    }
+
+    /// カーソルの位置に`c`を挿入し、カーソルを1文字進める
+    pub fn insert_char(&mut self, c: char) {
+        let cursor = self.cursor();
+        let mut value: Vec<char> = self.value().chars().collect();
+        value.insert(cursor, c);
+        self.value = Some(value.into_iter().collect());
+        self.cursor = Some(cursor + 1);
+    }
+
+    /// カーソルの直前の1文字を削除する。カーソルが先頭にある場合は何もせずfalseを返す
+    pub fn delete_char_before_cursor(&mut self) -> bool {
+        let cursor = self.cursor();
+        if cursor == 0 {
+            return false;
+        }
+        let mut value: Vec<char> = self.value().chars().collect();
+        value.remove(cursor - 1);
+        self.value = Some(value.into_iter().collect());
+        self.cursor = Some(cursor - 1);
+        true
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-classlist
+    /// class属性をASCII空白で区切り、重複を除いたトークンの列を返す
+    pub fn class_list(&self) -> Vec<String> {
+        let mut tokens: Vec<String> = Vec::new();
+        if let Some(class) = self.get_attribute("class") {
+            for token in class.split_ascii_whitespace() {
+                if !tokens.iter().any(|t| t == token) {
+                    tokens.push(token.to_string());
+                }
+            }
+        }
+        tokens
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-dtl-update
+    /// class属性がなく、トークンも空の場合は属性を作らない
+    fn set_class_list(&mut self, tokens: &[String]) {
+        if tokens.is_empty() && self.get_attribute("class").is_none() {
+            return;
+        }
+        self.set_attribute("class", &tokens.join(" "));
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-contains
+    pub fn has_class(&self, class: &str) -> bool {
+        self.class_list().iter().any(|t| t == class)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-add
+    pub fn add_class(&mut self, class: &str) {
+        let mut tokens = self.class_list();
+        if !tokens.iter().any(|t| t == class) {
+            tokens.push(class.to_string());
+        }
+        self.set_class_list(&tokens);
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-remove
+    pub fn remove_class(&mut self, class: &str) {
+        let mut tokens = self.class_list();
+        tokens.retain(|t| t != class);
+        self.set_class_list(&tokens);
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-toggle
+    /// 操作後にクラスが含まれていればtrueを返す
+    pub fn toggle_class(&mut self, class: &str) -> bool {
+        if self.has_class(class) {
+            self.remove_class(class);
+            false
+        } else {
+            self.add_class(class);
+            true
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -157,6 +592,12 @@ pub enum ElementKind {
     Html,
     /// https://html.spec.whatwg.org/multipage/semantics.html#the-head-element
     Head,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#the-base-element
+    Base,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#the-link-element
+    Link,
+    /// https://html.spec.whatwg.org/multipage/scripting.html#the-noscript-element
+    Noscript,
     /// https://html.spec.whatwg.org/multipage/semantics.html#the-style-element
     Style,
     /// https://html.spec.whatwg.org/multipage/scripting.html#the-script-element
@@ -168,8 +609,25 @@ pub enum ElementKind {
     /// https://html.spec.whatwg.org/multipage/sections.html#the-h1,-h2,-h3,-h4,-h5,-and-h6-elements
     H1,
     H2,
+    /// https://html.spec.whatwg.org/multipage/obsolete.html#center
+    /// 廃止された要素だが、古いページのために<div>と同じブロック要素として扱う
+    Center,
     /// https://html.spec.whatwg.org/multipage/text-level-semantics.html#the-a-element
     A,
+    /// https://html.spec.whatwg.org/multipage/embedded-content.html#the-img-element
+    Img,
+    /// https://html.spec.whatwg.org/multipage/forms.html#the-form-element
+    Form,
+    /// https://html.spec.whatwg.org/multipage/input.html#the-input-element
+    Input,
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#the-textarea-element
+    Textarea,
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#the-select-element
+    Select,
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#the-optgroup-element
+    Optgroup,
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#the-option-element
+    Option,
 }
 
 impl FromStr for ElementKind {
@@ -179,10 +637,392 @@ impl FromStr for ElementKind {
         match s {
             "html" => Ok(ElementKind::Html),
             "head" => Ok(ElementKind::Head),
+            "base" => Ok(ElementKind::Base),
+            "link" => Ok(ElementKind::Link),
+            "noscript" => Ok(ElementKind::Noscript),
             "style" => Ok(ElementKind::Style),
             "script" => Ok(ElementKind::Script),
             "body" => Ok(ElementKind::Body),
+            "p" => Ok(ElementKind::P),
+            "h1" => Ok(ElementKind::H1),
+            "h2" => Ok(ElementKind::H2),
+            "center" => Ok(ElementKind::Center),
+            "a" => Ok(ElementKind::A),
+            "img" => Ok(ElementKind::Img),
+            "form" => Ok(ElementKind::Form),
+            "input" => Ok(ElementKind::Input),
+            "textarea" => Ok(ElementKind::Textarea),
+            "select" => Ok(ElementKind::Select),
+            "optgroup" => Ok(ElementKind::Optgroup),
+            "option" => Ok(ElementKind::Option),
             _ => Err(format!("unimplemented element name {:?}", s)),
         }
     }
-}
\ No newline at end of file
+}
+
+impl ElementKind {
+    pub fn tag_name(&self) -> &'static str {
+        match self {
+            ElementKind::Html => "html",
+            ElementKind::Head => "head",
+            ElementKind::Base => "base",
+            ElementKind::Link => "link",
+            ElementKind::Noscript => "noscript",
+            ElementKind::Style => "style",
+            ElementKind::Script => "script",
+            ElementKind::Body => "body",
+            ElementKind::P => "p",
+            ElementKind::H1 => "h1",
+            ElementKind::H2 => "h2",
+            ElementKind::Center => "center",
+            ElementKind::A => "a",
+            ElementKind::Img => "img",
+            ElementKind::Form => "form",
+            ElementKind::Input => "input",
+            ElementKind::Textarea => "textarea",
+            ElementKind::Select => "select",
+            ElementKind::Optgroup => "optgroup",
+            ElementKind::Option => "option",
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/syntax.html#void-elements
+    pub fn is_void(&self) -> bool {
+        matches!(
+            self,
+            ElementKind::Base | ElementKind::Link | ElementKind::Img | ElementKind::Input
+        )
+    }
+
+    /// https://html.spec.whatwg.org/multipage/syntax.html#raw-text-elements
+    /// 内容を文字参照として解釈しない要素。子のテキストはエスケープせずに出力する
+    pub fn is_raw_text(&self) -> bool {
+        matches!(self, ElementKind::Style | ElementKind::Script)
+    }
+}
+
+fn first_child_element<F>(node: &Rc<RefCell<Node>>, predicate: F) -> Option<Rc<RefCell<Node>>>
+where
+    F: Fn(ElementKind) -> bool,
+{
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        if let Some(kind) = c.borrow().element_kind() {
+            if predicate(kind) {
+                return Some(c.clone());
+            }
+        }
+        child = c.borrow().next_sibling();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use alloc::vec;
+
+    #[test]
+    fn test_resolve_url_with_base_url() {
+        let mut window = Window::new();
+        let base_url = Url::new("http://example.com/dir/index.html".to_string())
+            .parse()
+            .expect("failed to parse base url");
+        window.set_base_url(base_url.clone());
+        assert_eq!(Some(base_url), window.base_url());
+
+        let url = window
+            .resolve_url("page.html")
+            .expect("failed to resolve url");
+        assert_eq!("example.com", url.host());
+        assert_eq!("dir/page.html", url.path());
+    }
+
+    #[test]
+    fn test_resolve_url_without_base_url() {
+        let window = Window::new();
+        assert_eq!(None, window.base_url());
+        assert!(window.resolve_url("page.html").is_err());
+
+        let url = window
+            .resolve_url("http://example.com/page.html")
+            .expect("failed to resolve url");
+        assert_eq!("page.html", url.path());
+    }
+
+    fn element(window: &mut Window, name: &str) -> Rc<RefCell<Node>> {
+        let node = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            name,
+            Vec::new(),
+        )))));
+        let document = window.document();
+        window.append_child(&document, node.clone());
+        node
+    }
+
+    #[test]
+    fn test_set_and_get_attribute() {
+        let mut window = Window::new();
+        let p = element(&mut window, "p");
+        assert_eq!(None, window.get_attribute(&p, "class"));
+
+        window.set_attribute(&p, "class", "a");
+        assert_eq!(Some("a".to_string()), window.get_attribute(&p, "class"));
+
+        // 既存の属性は上書きされ、重複しない
+        window.set_attribute(&p, "class", "b");
+        assert_eq!(Some("b".to_string()), window.get_attribute(&p, "class"));
+        assert_eq!(1, p.borrow().get_element().unwrap().attributes().len());
+    }
+
+    #[test]
+    fn test_remove_attribute() {
+        let mut window = Window::new();
+        let p = element(&mut window, "p");
+        window.set_attribute(&p, "class", "a");
+        window.set_attribute(&p, "title", "t");
+
+        window.remove_attribute(&p, "class");
+        assert_eq!(None, window.get_attribute(&p, "class"));
+        assert_eq!(Some("t".to_string()), window.get_attribute(&p, "title"));
+
+        // 存在しない属性を取り除いても何も起きない
+        window.remove_attribute(&p, "class");
+        assert_eq!(1, p.borrow().get_element().unwrap().attributes().len());
+    }
+
+    #[test]
+    fn test_attribute_updates_id_index() {
+        let mut window = Window::new();
+        let p = element(&mut window, "p");
+        assert!(window.get_element_by_id("a").is_none());
+
+        window.set_attribute(&p, "id", "a");
+        let found = window.get_element_by_id("a").expect("failed to find an element");
+        assert!(Rc::ptr_eq(&p, &found));
+
+        window.set_attribute(&p, "id", "b");
+        assert!(window.get_element_by_id("a").is_none());
+        assert!(window.get_element_by_id("b").is_some());
+
+        window.remove_attribute(&p, "id");
+        assert!(window.get_element_by_id("b").is_none());
+    }
+
+    #[test]
+    fn test_class_list() {
+        let mut e = Element::new(
+            "p",
+            vec![Attribute::from_name_and_value(
+                "class".to_string(),
+                "  a b\ta ".to_string(),
+            )],
+        );
+        assert_eq!(vec!["a".to_string(), "b".to_string()], e.class_list());
+        assert!(e.has_class("a"));
+        assert!(!e.has_class("c"));
+
+        // 追加すると重複が取り除かれ、順序を保ったまま空白1つで区切られる
+        e.add_class("c");
+        e.add_class("a");
+        assert_eq!(Some("a b c".to_string()), e.get_attribute("class"));
+
+        e.remove_class("b");
+        assert_eq!(Some("a c".to_string()), e.get_attribute("class"));
+
+        assert!(!e.toggle_class("a"));
+        assert_eq!(Some("c".to_string()), e.get_attribute("class"));
+        assert!(e.toggle_class("a"));
+        assert_eq!(Some("c a".to_string()), e.get_attribute("class"));
+    }
+
+    #[test]
+    fn test_class_list_without_class_attribute() {
+        let mut e = Element::new("p", Vec::new());
+        assert!(e.class_list().is_empty());
+        e.remove_class("a");
+        assert_eq!(None, e.get_attribute("class"));
+        assert!(e.toggle_class("a"));
+        assert_eq!(Some("a".to_string()), e.get_attribute("class"));
+    }
+
+    fn collect_ids(node: &Rc<RefCell<Node>>, ids: &mut Vec<NodeId>) {
+        ids.push(node.borrow().id());
+        let mut child = node.borrow().first_child();
+        while let Some(c) = child {
+            collect_ids(&c, ids);
+            child = c.borrow().next_sibling();
+        }
+    }
+
+    #[test]
+    fn test_node_id() {
+        let mut window = Window::new();
+        let p = element(&mut window, "p");
+        let a = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            "a",
+            Vec::new(),
+        )))));
+        window.append_child(&p, a.clone());
+        window.append_child(&p, Rc::new(RefCell::new(Node::new(NodeKind::Text("x".to_string())))));
+
+        let mut ids = Vec::new();
+        collect_ids(&window.document(), &mut ids);
+        assert_eq!(4, ids.len());
+        // 全てのノードが異なるidを持つ
+        for (i, id) in ids.iter().enumerate() {
+            assert!(!ids[i + 1..].contains(id));
+        }
+
+        // 走査し直しても同じidになる
+        let mut again = Vec::new();
+        collect_ids(&window.document(), &mut again);
+        assert_eq!(ids, again);
+
+        let found = window.get_node(a.borrow().id()).expect("failed to find a node");
+        assert!(Rc::ptr_eq(&a, &found));
+        let found = window.get_node(p.borrow().id()).expect("failed to find a node");
+        assert!(Rc::ptr_eq(&p, &found));
+    }
+
+    #[test]
+    fn test_node_id_after_append_child() {
+        let mut window = Window::new();
+        let p = element(&mut window, "p");
+        assert!(window.get_node(p.borrow().id()).is_some());
+
+        // 後から追加されたノードも引ける
+        let a = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            "a",
+            Vec::new(),
+        )))));
+        let id = a.borrow().id();
+        window.append_child(&p, a.clone());
+        let found = window.get_node(id).expect("failed to find a node");
+        assert!(Rc::ptr_eq(&a, &found));
+
+        // 破棄されたノードは引けない
+        window.remove_child(&p, &a);
+        drop(found);
+        drop(a);
+        assert!(window.get_node(id).is_none());
+
+        // Windowを通さずに追加されたノードは登録されていない
+        let b = Rc::new(RefCell::new(Node::new(NodeKind::Text("b".to_string()))));
+        append_child(&p, b.clone());
+        assert!(window.get_node(b.borrow().id()).is_none());
+    }
+
+    fn collect_weak_refs(node: &Rc<RefCell<Node>>, refs: &mut Vec<Weak<RefCell<Node>>>) {
+        refs.push(Rc::downgrade(node));
+        let mut child = node.borrow().first_child();
+        while let Some(c) = child {
+            collect_weak_refs(&c, refs);
+            child = c.borrow().next_sibling();
+        }
+    }
+
+    #[test]
+    fn test_tree_is_freed_after_dropping_window() {
+        let html = "<html><head><base href=\"http://example.com/\"></head><body><p id=\"a\">x<a href=\"b\">y</a>z</p><p>w</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        // idの索引とNodeIdの表も作っておく
+        let p = window.borrow_mut().get_element_by_id("a").expect("failed to find an element");
+        assert!(window.borrow_mut().get_node(p.borrow().id()).is_some());
+        drop(p);
+
+        let mut refs = Vec::new();
+        collect_weak_refs(&window.borrow().document(), &mut refs);
+        assert_eq!(12, refs.len());
+        // 各ノードを強く参照しているのは、親(最初の子の場合)か前の兄弟だけ
+        for node in &refs {
+            assert_eq!(1, node.strong_count());
+        }
+
+        assert_eq!(1, Rc::strong_count(&window));
+        drop(window);
+        for node in &refs {
+            assert!(node.upgrade().is_none());
+        }
+    }
+
+    #[test]
+    fn test_removed_subtree_is_freed() {
+        let html = "<html><head></head><body><p>x<a>y</a></p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let document = window.borrow().document();
+        let html = document.borrow().first_child().expect("failed to get html");
+        let body = html.borrow().last_child().upgrade().expect("failed to get body");
+
+        let mut refs = Vec::new();
+        {
+            let p = body.borrow().first_child().expect("failed to get p");
+            collect_weak_refs(&p, &mut refs);
+            assert!(window.borrow_mut().remove_child(&body, &p));
+        }
+        assert_eq!(4, refs.len());
+        for node in &refs {
+            assert!(node.upgrade().is_none());
+        }
+    }
+
+    #[test]
+    fn test_input_model() {
+        let html = "<html><head></head><body><input name=\"q\" value=\"initial\"><input type=\"PASSWORD\"></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construction_tree();
+        let document = window.borrow().document();
+        let html = document.borrow().first_child().expect("failed to get html");
+        let body = html.borrow().last_child().upgrade().expect("failed to get body");
+        let first = body.borrow().first_child().expect("failed to get an input");
+        let second = first.borrow().next_sibling().expect("failed to get an input");
+
+        let input = first.borrow().get_element().expect("failed to get an element");
+        assert_eq!(ElementKind::Input, input.kind());
+        assert_eq!("text", input.input_type());
+        assert_eq!(Some("q".to_string()), input.get_attribute("name"));
+        assert_eq!("initial", input.value());
+
+        let input = second.borrow().get_element().expect("failed to get an element");
+        assert_eq!("password", input.input_type());
+        assert_eq!("", input.value());
+
+        // 値を変更しても、value属性は変わらない
+        if let NodeKind::Element(ref mut e) = first.borrow_mut().kind {
+            e.set_value("changed");
+        }
+        let input = first.borrow().get_element().expect("failed to get an element");
+        assert_eq!("changed", input.value());
+        assert_eq!(Some("initial".to_string()), input.get_attribute("value"));
+    }
+
+    #[test]
+    fn test_document_element_and_body() {
+        for html in [
+            "<!-- comment --><html><head></head><body id=b><p>a</p></body></html>",
+            "<p>a</p>",
+        ] {
+            let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construction_tree();
+            let window = window.borrow();
+            let root = window.document_element().expect("failed to get document element");
+            assert_eq!(Some(ElementKind::Html), root.borrow().element_kind());
+            let body = window.body().expect("failed to get body");
+            assert_eq!(Some(ElementKind::Body), body.borrow().element_kind());
+            assert!(Rc::ptr_eq(
+                &root,
+                &body.borrow().parent().upgrade().expect("failed to get parent")
+            ));
+        }
+
+        let window = Window::new();
+        assert!(window.document_element().is_none());
+        assert!(window.body().is_none());
+
+        // 文書の終わりで<body>に達していなければ、<body>はない
+        let window =
+            HtmlParser::new(HtmlTokenizer::new("<html><head>".to_string())).construction_tree();
+        assert!(window.borrow().document_element().is_some());
+        assert!(window.borrow().body().is_none());
+    }
+}
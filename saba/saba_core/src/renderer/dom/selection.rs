@@ -0,0 +1,233 @@
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// https://dom.spec.whatwg.org/#concept-range-bp
+/// テキストノードと、その中の文字単位の位置
+#[derive(Debug, Clone)]
+pub struct Position {
+    node: Rc<RefCell<Node>>,
+    offset: usize,
+}
+
+impl Position {
+    pub fn new(node: Rc<RefCell<Node>>, offset: usize) -> Self {
+        Self { node, offset }
+    }
+
+    pub fn node(&self) -> Rc<RefCell<Node>> {
+        self.node.clone()
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// https://w3c.github.io/selection-api/#selection-interface
+/// 選択を始めた位置(anchor)と、現在の位置(focus)を持つ。focusがanchorより前にある
+/// 逆向きの選択も扱える
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    anchor: Option<Position>,
+    focus: Option<Position>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Self {
+            anchor: None,
+            focus: None,
+        }
+    }
+
+    pub fn anchor(&self) -> Option<Position> {
+        self.anchor.clone()
+    }
+
+    pub fn focus(&self) -> Option<Position> {
+        self.focus.clone()
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-setbaseandextent
+    pub fn set_base_and_extent(&mut self, anchor: Position, focus: Position) {
+        self.anchor = Some(anchor);
+        self.focus = Some(focus);
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-extend
+    /// anchorはそのままで、focusのみを動かす。選択がない場合はその位置に折り畳む
+    pub fn extend(&mut self, focus: Position) {
+        if self.anchor.is_none() {
+            self.anchor = Some(focus.clone());
+        }
+        self.focus = Some(focus);
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-collapse
+    pub fn collapse(&mut self, position: Position) {
+        self.anchor = Some(position.clone());
+        self.focus = Some(position);
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-removeallranges
+    pub fn clear(&mut self) {
+        self.anchor = None;
+        self.focus = None;
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-iscollapsed
+    pub fn is_collapsed(&self) -> bool {
+        match (&self.anchor, &self.focus) {
+            (Some(a), Some(f)) => Rc::ptr_eq(&a.node, &f.node) && a.offset == f.offset,
+            _ => true,
+        }
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-tostring
+    /// 選択範囲に含まれる文字を文書順に連結して返す
+    pub fn selected_text(&self) -> String {
+        let (anchor, focus) = match (&self.anchor, &self.focus) {
+            (Some(a), Some(f)) => (a, f),
+            _ => return String::new(),
+        };
+
+        let text_nodes = text_nodes_in_document_order(&root(&anchor.node));
+        let anchor_index = match text_nodes.iter().position(|n| Rc::ptr_eq(n, &anchor.node)) {
+            Some(i) => i,
+            None => return String::new(),
+        };
+        let focus_index = match text_nodes.iter().position(|n| Rc::ptr_eq(n, &focus.node)) {
+            Some(i) => i,
+            None => return String::new(),
+        };
+
+        // 逆向きの選択の場合は、文書順に並べ替える
+        let ((start_index, start_offset), (end_index, end_offset)) =
+            if (anchor_index, anchor.offset) <= (focus_index, focus.offset) {
+                ((anchor_index, anchor.offset), (focus_index, focus.offset))
+            } else {
+                ((focus_index, focus.offset), (anchor_index, anchor.offset))
+            };
+
+        let mut result = String::new();
+        for (i, node) in text_nodes
+            .iter()
+            .enumerate()
+            .take(end_index + 1)
+            .skip(start_index)
+        {
+            let text = match node.borrow().kind {
+                NodeKind::Text(ref s) => s.clone(),
+                _ => continue,
+            };
+            let from = if i == start_index { start_offset } else { 0 };
+            let to = if i == end_index {
+                end_offset
+            } else {
+                text.chars().count()
+            };
+            result.extend(text.chars().skip(from).take(to.saturating_sub(from)));
+        }
+        result
+    }
+}
+
+fn root(node: &Rc<RefCell<Node>>) -> Rc<RefCell<Node>> {
+    let mut current = node.clone();
+    loop {
+        let parent = current.borrow().parent().upgrade();
+        match parent {
+            Some(p) => current = p,
+            None => return current,
+        }
+    }
+}
+
+fn text_nodes_in_document_order(node: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    let mut result = Vec::new();
+    collect_text_nodes(node, &mut result);
+    result
+}
+
+fn collect_text_nodes(node: &Rc<RefCell<Node>>, result: &mut Vec<Rc<RefCell<Node>>>) {
+    if let NodeKind::Text(_) = node.borrow().kind {
+        result.push(node.clone());
+    }
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        collect_text_nodes(&c, result);
+        child = c.borrow().next_sibling();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::dom::node::Window;
+    use crate::renderer::html::token::HtmlTokenizer;
+
+    /// ノードは親を弱参照で持つので、テストの間はWindowを生かしておく
+    fn parse() -> Rc<RefCell<Window>> {
+        let html = "<html><head></head><body><p>hello</p><p>world</p></body></html>".to_string();
+        HtmlParser::new(HtmlTokenizer::new(html)).construction_tree()
+    }
+
+    fn text_nodes(window: &Rc<RefCell<Window>>) -> Vec<Rc<RefCell<Node>>> {
+        let nodes = text_nodes_in_document_order(&window.borrow().document());
+        assert_eq!(2, nodes.len());
+        nodes
+    }
+
+    #[test]
+    fn test_selection_across_text_nodes() {
+        let window = parse();
+        let nodes = text_nodes(&window);
+        let (hello, world) = (nodes[0].clone(), nodes[1].clone());
+        let mut selection = Selection::new();
+        selection.set_base_and_extent(Position::new(hello, 3), Position::new(world, 2));
+        assert!(!selection.is_collapsed());
+        assert_eq!("lowo", selection.selected_text());
+    }
+
+    #[test]
+    fn test_reversed_selection() {
+        let window = parse();
+        let nodes = text_nodes(&window);
+        let (hello, world) = (nodes[0].clone(), nodes[1].clone());
+        let mut selection = Selection::new();
+        selection.set_base_and_extent(Position::new(world.clone(), 2), Position::new(hello, 3));
+        assert_eq!("lowo", selection.selected_text());
+
+        // 同じノードの中での逆向きの選択
+        selection.set_base_and_extent(Position::new(world.clone(), 4), Position::new(world, 1));
+        assert_eq!("orl", selection.selected_text());
+    }
+
+    #[test]
+    fn test_collapsed_selection() {
+        let window = parse();
+        let nodes = text_nodes(&window);
+        let (hello, world) = (nodes[0].clone(), nodes[1].clone());
+        let mut selection = Selection::new();
+        assert!(selection.is_collapsed());
+        assert_eq!("", selection.selected_text());
+
+        selection.collapse(Position::new(hello.clone(), 1));
+        assert!(selection.is_collapsed());
+        assert_eq!("", selection.selected_text());
+
+        selection.extend(Position::new(world, 5));
+        assert_eq!("elloworld", selection.selected_text());
+
+        selection.clear();
+        assert!(selection.anchor().is_none());
+        assert_eq!("", selection.selected_text());
+    }
+}
@@ -0,0 +1,391 @@
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use crate::renderer::html::attribute::Attribute;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// 属性を出力する順番
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AttributeOrder {
+    /// 属性名の昇順に並べ替えて出力する
+    Sorted,
+    /// 元のHTML文書に書かれていた順番のまま、元の引用符(ダブルクォート・シングルクォート・
+    /// 引用符なし)で出力する
+    Original,
+}
+
+/// テキストノードの空白の出力方法
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Whitespace {
+    /// テキストノードの内容をそのまま出力する
+    Verbatim,
+    /// 連続するASCII空白を1つの空白にまとめ、空白だけのテキストノードは出力しない。
+    /// テストで、意味のない空白の違いを気にせずに構造を比較するために使う
+    Normalized,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SerializeOptions {
+    pub attribute_order: AttributeOrder,
+    pub whitespace: Whitespace,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self {
+            attribute_order: AttributeOrder::Sorted,
+            whitespace: Whitespace::Verbatim,
+        }
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments
+/// ノードとその子孫をHTML文字列に変換する。Documentノードの場合は子のみを出力する。
+/// 属性値はAttributeOrder::Originalの場合を除き、ダブルクォートで囲んで出力する。
+/// style要素とscript要素の中のテキストはエスケープしない
+pub fn serialize(node: &Rc<RefCell<Node>>, options: &SerializeOptions) -> String {
+    let mut result = String::new();
+    serialize_node(node, options, &mut result);
+    result
+}
+
+fn serialize_node(node: &Rc<RefCell<Node>>, options: &SerializeOptions, result: &mut String) {
+    let kind = node.borrow().kind();
+    match kind {
+        NodeKind::Document => serialize_children(node, options, result),
+        NodeKind::Element(ref e) => {
+            let tag_name = e.kind().tag_name();
+            result.push('<');
+            result.push_str(tag_name);
+            for attr in ordered_attributes(e.attributes(), options.attribute_order) {
+                result.push(' ');
+                result.push_str(&attr.name());
+                let quote = match options.attribute_order {
+                    AttributeOrder::Sorted => Some('"'),
+                    AttributeOrder::Original => original_quote(&attr),
+                };
+                match quote {
+                    Some(quote) => {
+                        result.push('=');
+                        result.push(quote);
+                        escape_attribute(&attr.value(), quote, result);
+                        result.push(quote);
+                    }
+                    None if attr.value().is_empty() => {}
+                    None => {
+                        result.push('=');
+                        escape_attribute(&attr.value(), ' ', result);
+                    }
+                }
+            }
+            result.push('>');
+
+            if e.kind().is_void() {
+                return;
+            }
+
+            serialize_children(node, options, result);
+            result.push_str("</");
+            result.push_str(tag_name);
+            result.push('>');
+        }
+        NodeKind::Text(ref s) => {
+            let raw_text = node
+                .borrow()
+                .parent()
+                .upgrade()
+                .and_then(|p| p.borrow().element_kind())
+                .is_some_and(|k| k.is_raw_text());
+            if raw_text {
+                result.push_str(s);
+                return;
+            }
+            match options.whitespace {
+                Whitespace::Verbatim => escape(s, false, result),
+                Whitespace::Normalized => escape(&collapse_whitespace(s), false, result),
+            }
+        }
+        NodeKind::Comment(ref s) => {
+            result.push_str("<!--");
+            result.push_str(s);
+            result.push_str("-->");
+        }
+    }
+}
+
+fn serialize_children(node: &Rc<RefCell<Node>>, options: &SerializeOptions, result: &mut String) {
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        serialize_node(&c, options, result);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// テストやデバッグで木構造を比べるために、ノードとその子孫を1行に1つずつ、深さに応じて
+/// 字下げして書き出す。html5libのテストの木の形式にならい、属性は要素の1段下に名前の順に並べる
+///
+/// ```text
+/// <html>
+///   <head>
+///   <body>
+///     <p>
+///       id="a"
+///       "text"
+/// ```
+pub fn dump_tree(node: &Rc<RefCell<Node>>) -> String {
+    let mut lines = Vec::new();
+    match node.borrow().kind() {
+        NodeKind::Document => {
+            let mut child = node.borrow().first_child();
+            while let Some(c) = child {
+                dump_node(&c, 0, &mut lines);
+                child = c.borrow().next_sibling();
+            }
+        }
+        _ => dump_node(node, 0, &mut lines),
+    }
+    lines.join("\n")
+}
+
+fn dump_node(node: &Rc<RefCell<Node>>, depth: usize, lines: &mut Vec<String>) {
+    dump_kind(&node.borrow().kind, depth, lines);
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        dump_node(&c, depth + 1, lines);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// dump_treeの1つのノード分の行。arenaのダンプと書式を揃えるために共有する
+pub(crate) fn dump_kind(kind: &NodeKind, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    match kind {
+        NodeKind::Document => lines.push(format!("{}#document", indent)),
+        NodeKind::Element(ref e) => {
+            lines.push(format!("{}<{}>", indent, e.kind().tag_name()));
+            for attr in ordered_attributes(e.attributes(), AttributeOrder::Sorted) {
+                lines.push(format!("{}  {}=\"{}\"", indent, attr.name(), attr.value()));
+            }
+        }
+        NodeKind::Text(ref s) => lines.push(format!("{}\"{}\"", indent, s)),
+        NodeKind::Comment(ref s) => lines.push(format!("{}<!-- {} -->", indent, s)),
+    }
+}
+
+fn ordered_attributes(mut attributes: Vec<Attribute>, order: AttributeOrder) -> Vec<Attribute> {
+    if order == AttributeOrder::Sorted {
+        attributes.sort_by_key(|a| a.name());
+    }
+    attributes
+}
+
+/// 連続するASCII空白を1つの空白にまとめる。空白だけの文字列は空文字列になる
+fn collapse_whitespace(s: &str) -> String {
+    if s.chars().all(|c| c.is_ascii_whitespace()) {
+        return String::new();
+    }
+
+    let mut result = String::new();
+    let mut last_was_whitespace = false;
+    for c in s.chars() {
+        if c.is_ascii_whitespace() {
+            if !last_was_whitespace {
+                result.push(' ');
+            }
+            last_was_whitespace = true;
+        } else {
+            result.push(c);
+            last_was_whitespace = false;
+        }
+    }
+    result
+}
+
+/// AttributeOrder::Originalで`attr`を囲む引用符。元の文書で引用符がなかった値は、
+/// 引用符なしでは読み戻せない文字を含む場合だけダブルクォートで囲む。
+/// Noneを返した場合は、値が空なら属性名だけを、そうでなければ値をそのまま出力する
+fn original_quote(attr: &Attribute) -> Option<char> {
+    if attr.quote().is_some() {
+        return attr.quote();
+    }
+    let value = attr.value();
+    let needs_quote = value
+        .chars()
+        .any(|c| c.is_ascii_whitespace() || matches!(c, '"' | '\'' | '=' | '<' | '>' | '`'));
+    if needs_quote {
+        Some('"')
+    } else {
+        None
+    }
+}
+
+/// 属性値を`quote`で囲んで出力するためにエスケープする。シングルクォートで囲む場合は、
+/// ダブルクォートの代わりにシングルクォートを文字参照にする
+fn escape_attribute(s: &str, quote: char, result: &mut String) {
+    if quote != '\'' {
+        escape(s, true, result);
+        return;
+    }
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '\u{00A0}' => result.push_str("&nbsp;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#escapingString
+fn escape(s: &str, attribute_mode: bool, result: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '\u{00A0}' => result.push_str("&nbsp;"),
+            '"' if attribute_mode => result.push_str("&quot;"),
+            '<' if !attribute_mode => result.push_str("&lt;"),
+            '>' if !attribute_mode => result.push_str("&gt;"),
+            _ => result.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+
+    fn parse(html: &str) -> Rc<RefCell<Node>> {
+        let t = HtmlTokenizer::new(html.to_string());
+        let window = HtmlParser::new(t).construction_tree();
+        let document = window.borrow().document();
+        document
+    }
+
+    #[test]
+    fn test_serialize_sorted_attributes() {
+        let document =
+            parse("<html><head></head><body><p id=\"x\" class='y' data=z>t</p></body></html>");
+        assert_eq!(
+            "<html><head></head><body><p class=\"y\" data=\"z\" id=\"x\">t</p></body></html>",
+            serialize(&document, &SerializeOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_serialize_original_attribute_order() {
+        let document =
+            parse("<html><head></head><body><p id=\"x\" class='y' data=z>t</p></body></html>");
+        let options = SerializeOptions {
+            attribute_order: AttributeOrder::Original,
+            ..SerializeOptions::default()
+        };
+        assert_eq!(
+            "<html><head></head><body><p id=\"x\" class='y' data=z>t</p></body></html>",
+            serialize(&document, &options)
+        );
+    }
+
+    #[test]
+    fn test_serialize_original_quotes() {
+        let html = "<html><head></head><body><p title='say \"hi\"' class=a data-x=\"it's\">\
+            <input disabled type=text></p></body></html>";
+        let options = SerializeOptions {
+            attribute_order: AttributeOrder::Original,
+            ..SerializeOptions::default()
+        };
+        let document = parse(html);
+        assert_eq!(html, serialize(&document, &options));
+
+        // 引用符なしで書けない値に変わった場合は、ダブルクォートで囲む
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construction_tree();
+        let p = window
+            .borrow()
+            .body()
+            .and_then(|b| b.borrow().first_child())
+            .expect("failed to find p");
+        window
+            .borrow_mut()
+            .set_attribute(&p, "class", "a b");
+        assert!(serialize(&p, &options).starts_with("<p title='say \"hi\"' class=\"a b\""));
+    }
+
+    #[test]
+    fn test_serialize_raw_text_round_trip() {
+        let html = "<html><head><style>a > b { content: \"<\" }</style>\
+            <script>if (a < b && c) {}</script></head><body><p>a &gt; b</p></body></html>";
+        let document = parse(html);
+        let serialized = serialize(&document, &SerializeOptions::default());
+        assert_eq!(html, serialized);
+        assert_eq!(dump_tree(&document), dump_tree(&parse(&serialized)));
+    }
+
+    #[test]
+    fn test_serialize_escape() {
+        let document = parse(
+            "<html><head><base href='a?b=\"c\"&d'></head><body><p>1 &lt; 2 &amp; 3</p></body></html>",
+        );
+        assert_eq!(
+            "<html><head><base href=\"a?b=&quot;c&quot;&amp;d\"></head><body><p>1 &lt; 2 &amp; 3</p></body></html>",
+            serialize(&document, &SerializeOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_serialize_verbatim_whitespace() {
+        let document =
+            parse("<html><head></head><body><p>a \t\n  b</p><p>\t</p></body></html>");
+        assert_eq!(
+            "<html><head></head><body><p>a \t\n  b</p><p>\t</p></body></html>",
+            serialize(&document, &SerializeOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_serialize_normalized_whitespace() {
+        let document =
+            parse("<html><head></head><body><p>a \t\n  b</p><p>\t</p></body></html>");
+        let options = SerializeOptions {
+            whitespace: Whitespace::Normalized,
+            ..SerializeOptions::default()
+        };
+        assert_eq!(
+            "<html><head></head><body><p>a b</p><p></p></body></html>",
+            serialize(&document, &options)
+        );
+        assert_eq!(
+            serialize(&document, &options),
+            serialize(
+                &parse("<html><head></head><body><p>a b</p><p></p></body></html>"),
+                &options
+            )
+        );
+    }
+
+    #[test]
+    fn test_dump_tree() {
+        let document = parse(
+            "<!--c--><html><head></head><body><p id=\"x\" class=y>a<a href=b>c</a></p></body></html>",
+        );
+        let expected = [
+            "<!-- c -->",
+            "<html>",
+            "  <head>",
+            "  <body>",
+            "    <p>",
+            "      class=\"y\"",
+            "      id=\"x\"",
+            "      \"a\"",
+            "      <a>",
+            "        href=\"b\"",
+            "        \"c\"",
+        ];
+        assert_eq!(expected.join("\n"), dump_tree(&document));
+    }
+}
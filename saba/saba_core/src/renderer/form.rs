@@ -0,0 +1,274 @@
+use crate::error::Error;
+use crate::http::HttpRequest;
+use crate::http::Method;
+use crate::renderer::dom::api::form_controls;
+use crate::renderer::dom::api::text_content;
+use crate::renderer::dom::node::Element;
+use crate::renderer::dom::node::ElementKind;
+use crate::renderer::dom::node::Node;
+use crate::url::Url;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// フォームの送信先のURLと、送信するリクエスト
+#[derive(Debug, Clone)]
+pub struct FormSubmission {
+    url: Url,
+    request: HttpRequest,
+}
+
+impl FormSubmission {
+    /// クエリを含む送信先のURL
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    pub fn request(&self) -> HttpRequest {
+        self.request.clone()
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-submission-algorithm
+/// `form`のコントロールの名前と値の組からリクエストを作る。method属性が"post"であれば
+/// ボディに、それ以外であればクエリに値を入れる。送信先はaction属性を`base_url`で解決したURL
+pub fn submit_form(form: &Rc<RefCell<Node>>, base_url: &Url) -> Result<FormSubmission, Error> {
+    let element = match form.borrow().get_element() {
+        Some(e) if e.kind() == ElementKind::Form => e,
+        _ => {
+            return Err(Error::UnexpectedInput(
+                "form submission requires a form element".to_string(),
+            ))
+        }
+    };
+
+    let action = match element.get_attribute("action") {
+        Some(action) if !action.is_empty() => base_url.join(&action),
+        _ => Ok(base_url.clone()),
+    };
+    let action = match action {
+        Ok(url) => url,
+        Err(e) => return Err(Error::UnexpectedInput(e)),
+    };
+
+    let query = urlencode(&construct_entry_list(form));
+    let is_post = element
+        .get_attribute("method")
+        .map(|m| m.eq_ignore_ascii_case("post"))
+        .unwrap_or(false);
+
+    if is_post {
+        let mut request = HttpRequest::new(action.host(), request_path(&action));
+        request.set_method(Method::Post);
+        request.add_header(
+            "Content-Type".to_string(),
+            "application/x-www-form-urlencoded".to_string(),
+        );
+        request.set_body(query);
+        return Ok(FormSubmission {
+            url: action,
+            request,
+        });
+    }
+
+    // https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#submit-mutate-action
+    let url = match action.join(&format!("?{}", query)) {
+        Ok(url) => url,
+        Err(e) => return Err(Error::UnexpectedInput(e)),
+    };
+    let request = HttpRequest::new(url.host(), request_path(&url));
+    Ok(FormSubmission { url, request })
+}
+
+/// HttpRequestに渡す、クエリを含むパス
+fn request_path(url: &Url) -> String {
+    if url.searchpart().is_empty() {
+        url.path()
+    } else {
+        format!("{}?{}", url.path(), url.searchpart())
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#constructing-the-form-data-set
+/// name属性がないコントロールや、無効化されたコントロールは含めない
+fn construct_entry_list(form: &Rc<RefCell<Node>>) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    for control in form_controls(form) {
+        let element = match control.borrow().get_element() {
+            Some(e) => e,
+            None => continue,
+        };
+        if element.get_attribute("disabled").is_some() {
+            continue;
+        }
+        let name = match element.get_attribute("name") {
+            Some(name) if !name.is_empty() => name,
+            _ => continue,
+        };
+
+        let value = match element.kind() {
+            ElementKind::Input => match input_value(&element) {
+                Some(value) => value,
+                None => continue,
+            },
+            ElementKind::Textarea => text_content(&control),
+            ElementKind::Select => match selected_option_value(&control) {
+                Some(value) => value,
+                None => continue,
+            },
+            _ => continue,
+        };
+        entries.push((name, value));
+    }
+    entries
+}
+
+/// 送信されない<input>の場合はNoneを返す
+fn input_value(element: &Element) -> Option<String> {
+    match element.input_type().as_str() {
+        "checkbox" | "radio" => {
+            element.get_attribute("checked")?;
+            Some(element.get_attribute("value").unwrap_or("on".to_string()))
+        }
+        // 送信ボタンやファイルの送信はサポートしていない
+        "submit" | "reset" | "button" | "image" | "file" => None,
+        _ => Some(element.value()),
+    }
+}
+
+/// selected属性を持つ最初の<option>か、なければ最初の<option>の値を返す
+fn selected_option_value(select: &Rc<RefCell<Node>>) -> Option<String> {
+    let mut options = Vec::new();
+    collect_options(select, &mut options);
+
+    let selected = options
+        .iter()
+        .find(|o| {
+            o.borrow()
+                .get_element()
+                .map(|e| e.get_attribute("selected").is_some())
+                .unwrap_or(false)
+        })
+        .or(options.first())?;
+
+    let value = selected
+        .borrow()
+        .get_element()
+        .and_then(|e| e.get_attribute("value"));
+    Some(value.unwrap_or_else(|| text_content(selected)))
+}
+
+fn collect_options(node: &Rc<RefCell<Node>>, result: &mut Vec<Rc<RefCell<Node>>>) {
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        if c.borrow().element_kind() == Some(ElementKind::Option) {
+            result.push(c.clone());
+        }
+        collect_options(&c, result);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// https://url.spec.whatwg.org/#concept-urlencoded-serializer
+fn urlencode(entries: &[(String, String)]) -> String {
+    let mut result = String::new();
+    for (i, (name, value)) in entries.iter().enumerate() {
+        if i > 0 {
+            result.push('&');
+        }
+        percent_encode(name, &mut result);
+        result.push('=');
+        percent_encode(value, &mut result);
+    }
+    result
+}
+
+/// https://url.spec.whatwg.org/#application-x-www-form-urlencoded-percent-encode-set
+fn percent_encode(s: &str, result: &mut String) {
+    for b in s.bytes() {
+        match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => {
+                result.push(b as char)
+            }
+            b' ' => result.push('+'),
+            _ => result.push_str(&format!("%{:02X}", b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::get_element_by_id;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+
+    fn form(html: &str) -> Rc<RefCell<Node>> {
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construction_tree();
+        let document = window.borrow().document();
+        get_element_by_id(Some(document), "f").expect("failed to find a form")
+    }
+
+    fn base_url() -> Url {
+        Url::new("http://example.com:8000/dir/index.html".to_string())
+            .parse()
+            .expect("failed to parse url")
+    }
+
+    #[test]
+    fn test_submit_get_form() {
+        let form = form("<html><head></head><body><form id=\"f\" action=\"search\">\
+            <input name=\"q\" value=\"a b&c\">\
+            <input value=\"no name\">\
+            <input type=\"checkbox\" name=\"c\" checked>\
+            <input type=\"checkbox\" name=\"d\">\
+            <select name=\"s\"><option value=\"1\">one<option selected>two</select>\
+            <input type=\"submit\" name=\"go\" value=\"Go\">\
+            </form></body></html>");
+
+        let submission = submit_form(&form, &base_url()).expect("failed to submit a form");
+        let url = submission.url();
+        assert_eq!("example.com", url.host());
+        assert_eq!("8000", url.port());
+        assert_eq!("dir/search", url.path());
+        assert_eq!("q=a+b%26c&c=on&s=two", url.searchpart());
+
+        let request = submission.request();
+        assert_eq!(Method::Get, request.method());
+        assert_eq!("dir/search?q=a+b%26c&c=on&s=two", request.path());
+        assert_eq!(None, request.body());
+    }
+
+    #[test]
+    fn test_submit_post_form() {
+        let form = form("<html><head></head><body><form id=\"f\" action=\"/post\" method=\"POST\">\
+            <input name=\"user\" value=\"saba\">\
+            <textarea name=\"comment\">hello</textarea>\
+            <input name=\"disabled\" value=\"x\" disabled>\
+            </form></body></html>");
+
+        let submission = submit_form(&form, &base_url()).expect("failed to submit a form");
+        assert_eq!("post", submission.url().path());
+
+        let request = submission.request();
+        assert_eq!(Method::Post, request.method());
+        assert_eq!("post", request.path());
+        assert_eq!(Some("user=saba&comment=hello".to_string()), request.body());
+        assert!(request
+            .headers()
+            .iter()
+            .any(|h| h.name() == "Content-Type"
+                && h.value() == "application/x-www-form-urlencoded"));
+    }
+
+    #[test]
+    fn test_submit_form_without_action() {
+        let form = form("<html><head></head><body><form id=\"f\"><input name=\"q\" value=\"x\"></form></body></html>");
+        let submission = submit_form(&form, &base_url()).expect("failed to submit a form");
+        assert_eq!("dir/index.html", submission.url().path());
+        assert_eq!("q=x", submission.url().searchpart());
+    }
+}
@@ -1,16 +1,37 @@
 use alloc::string::String;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct Attribute {
     name: String,
     value: String,
+    /// 元のHTML文書で属性値を囲んでいた引用符('"'か'\'')。引用符がなかった場合や、
+    /// HTML文書から読み込んでいない場合はNone
+    quote: Option<char>,
 }
 
+/// 引用符は書き方の違いで値には影響しないので、名前と値だけを比べる
+impl PartialEq for Attribute {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.value == other.value
+    }
+}
+
+impl Eq for Attribute {}
+
 impl Attribute {
     pub fn new() -> Self {
         Self {
             name: String::new(),
             value: String::new(),
+            quote: None,
+        }
+    }
+
+    pub fn from_name_and_value(name: String, value: String) -> Self {
+        Self {
+            name,
+            value,
+            quote: None,
         }
     }
 
@@ -29,4 +50,16 @@ impl Attribute {
     pub fn value(&self) -> String {
         self.value.clone()
     }
+
+    pub fn set_value(&mut self, value: String) {
+        self.value = value;
+    }
+
+    pub fn quote(&self) -> Option<char> {
+        self.quote
+    }
+
+    pub fn set_quote(&mut self, quote: char) {
+        self.quote = Some(quote);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,49 @@
+/// https://html.spec.whatwg.org/multipage/named-characters.html
+/// 本書では全ての名前付き文字参照をサポートしていないため、よく使われるものだけを定義する
+const NAMED_CHARACTER_REFERENCES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{00A0}'),
+    ("iexcl", '\u{00A1}'),
+    ("cent", '\u{00A2}'),
+    ("pound", '\u{00A3}'),
+    ("yen", '\u{00A5}'),
+    ("sect", '\u{00A7}'),
+    ("copy", '\u{00A9}'),
+    ("laquo", '\u{00AB}'),
+    ("reg", '\u{00AE}'),
+    ("deg", '\u{00B0}'),
+    ("plusmn", '\u{00B1}'),
+    ("micro", '\u{00B5}'),
+    ("para", '\u{00B6}'),
+    ("middot", '\u{00B7}'),
+    ("raquo", '\u{00BB}'),
+    ("iquest", '\u{00BF}'),
+    ("times", '\u{00D7}'),
+    ("divide", '\u{00F7}'),
+    ("ndash", '\u{2013}'),
+    ("mdash", '\u{2014}'),
+    ("lsquo", '\u{2018}'),
+    ("rsquo", '\u{2019}'),
+    ("ldquo", '\u{201C}'),
+    ("rdquo", '\u{201D}'),
+    ("bull", '\u{2022}'),
+    ("hellip", '\u{2026}'),
+    ("euro", '\u{20AC}'),
+    ("trade", '\u{2122}'),
+    ("larr", '\u{2190}'),
+    ("uarr", '\u{2191}'),
+    ("rarr", '\u{2192}'),
+    ("darr", '\u{2193}'),
+];
+
+/// 名前付き文字参照の名前(`&`と`;`を除いた部分)から対応する文字を返す
+pub fn lookup_named_character_reference(name: &str) -> Option<char> {
+    NAMED_CHARACTER_REFERENCES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, c)| *c)
+}
@@ -1,3 +1,9 @@
 pub mod attribute;
+pub mod entity;
 pub mod parser;
-pub mod token;
\ No newline at end of file
+pub mod stats;
+pub mod token;
+#[cfg(test)]
+mod parser_harness;
+#[cfg(test)]
+mod token_harness;
\ No newline at end of file
@@ -1,3 +1,5 @@
+use crate::error::Error;
+use crate::renderer::dom::api::append_child;
 use crate::renderer::dom::node::Element;
 use crate::renderer::dom::node::ElementKind;
 use crate::renderer::dom::node::Node;
@@ -5,26 +7,64 @@ use crate::renderer::dom::node::NodeKind;
 use crate::renderer::dom::node::Window;
 use crate::renderer::html::token::HtmlTokenizer;
 use crate::renderer::html::token::HtmlToken;
+use crate::renderer::html::token::SourcePosition;
 use crate::renderer::html::attribute::Attribute;
+use alloc::format;
 use alloc::rc::Rc;
+use alloc::string::String;
 use alloc::vec::Vec;
 use core::cell::RefCell;
+use core::str::FromStr;
 
-use super::attribute;
-
+/// 開いている要素のスタックの深さの既定の上限
+pub const DEFAULT_MAX_DEPTH: usize = 512;
 
 /// https://html.spec.whatwg.org/multipage/parsing.html#the-insertion-mode
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum InsertionMode {
     Initial,
     BeforeHtml,
+    BeforeHead,
     InHead,
     AfterHead,
     InBody,
+    Text,
     AfterBody,
     AfterAfterBody,
 }
 
+/// https://html.spec.whatwg.org/multipage/parsing.html#parse-errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    description: String,
+    /// エラーが見つかったときのトークナイザの入力位置(文字単位のおおよその位置)
+    position: usize,
+    /// エラーの原因となったトークンの開始位置
+    source_position: SourcePosition,
+}
+
+impl ParseError {
+    pub fn new(description: String, position: usize, source_position: SourcePosition) -> Self {
+        Self {
+            description,
+            position,
+            source_position,
+        }
+    }
+
+    pub fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn source_position(&self) -> SourcePosition {
+        self.source_position
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HtmlParser {
     window: Rc<RefCell<Window>>,
@@ -33,6 +73,17 @@ pub struct HtmlParser {
     original_insertion_mode: InsertionMode,
     /// https://html.spec.whatwg.org/multipage/parsing.html#the-stack-of-open-elements
     stack_of_open_elements: Vec<Rc<RefCell<Node>>>,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#frozen-base-url
+    /// 最初の<base>要素のみが基準URLを決めるため、一度設定されたら以降の<base>は無視する
+    base_url_frozen: bool,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#head-element-pointer
+    head_element: Option<Rc<RefCell<Node>>>,
+    /// 開いている要素のスタックの深さの上限。これを超える要素は無視する
+    max_depth: usize,
+    /// パース中に見つかったエラー。エラーがあってもツリーの構築は続ける
+    parse_errors: Vec<ParseError>,
+    /// trueの場合、try_construction_treeはパースエラーがあるとエラーを返す
+    strict: bool,
     t: HtmlTokenizer,
 }
 
@@ -43,9 +94,39 @@ impl HtmlParser {
             mode:   InsertionMode::Initial,
             original_insertion_mode: InsertionMode::Initial,
             stack_of_open_elements: Vec::new(),
+            base_url_frozen: false,
+            head_element: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+            parse_errors: Vec::new(),
+            strict: false,
             t,
         }
-    } 
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn set_scripting_enabled(&mut self, scripting_enabled: bool) {
+        self.window
+            .borrow_mut()
+            .set_scripting_enabled(scripting_enabled);
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn parse_errors(&self) -> Vec<ParseError> {
+        self.parse_errors.clone()
+    }
+
+    fn parse_error(&mut self, description: String) {
+        let position = self.t.position();
+        let source_position = self.t.token_position();
+        self.parse_errors
+            .push(ParseError::new(description, position, source_position));
+    }
 
     fn contain_in_stack(&mut self, element_kind: ElementKind) -> bool {
         for i in 0..self.stack_of_open_elements.len() {
@@ -57,12 +138,12 @@ impl HtmlParser {
         false
     }
 
+    /// `element_kind`の要素が取り除かれるまでスタックから取り除く。入れ子の深さの上限で
+    /// 要素が挿入されなかった場合など、スタックにその要素がなければ何もしない
     fn pop_until(&mut self, element_kind: ElementKind) {
-        assert!(
-            self.contain_in_stack(element_kind),
-            "stack doesn't have an element {:?}",
-            element_kind,
-        );
+        if !self.contain_in_stack(element_kind) {
+            return;
+        }
 
         loop {
             let current = match self.stack_of_open_elements.pop() {
@@ -90,68 +171,264 @@ impl HtmlParser {
         false
     }
 
-    fn create_element(&self, tag: &str, attribute: Vec<Attribute>) -> Node {
+    /// テキストノードは子を持てないため、スタックの最上部にあるテキストノードを取り除く
+    fn pop_text_node(&mut self) {
+        let is_text = match self.stack_of_open_elements.last() {
+            Some(n) => matches!(n.borrow().kind(), NodeKind::Text(_)),
+            None => false,
+        };
+
+        if is_text {
+            self.stack_of_open_elements.pop();
+        }
+    }
+
+    fn create_element(&self, tag: &str, attributes: Vec<Attribute>) -> Node {
         Node::new(NodeKind::Element(Element::new(tag, attributes)))
     }
 
-    fn insert_element(&mut self, tag: &str, attributes: Vec<Attribute>) {
-        let window = self.window.borrow();
+    /// 要素を挿入してスタックに積む。入れ子の深さの上限を超えて挿入しなかった場合はfalseを返すので、
+    /// 呼び出し元は挿入した要素を取り除いたり、挿入モードを切り替えたりしてはいけない
+    fn insert_element(&mut self, tag: &str, attributes: Vec<Attribute>) -> bool {
+        // 極端に深くネストされた文書でスタックやツリーが際限なく深くなり、
+        // 再帰的な走査でスタックオーバーフローが起きるのを防ぐため、上限を超える要素は無視する
+        if self.stack_of_open_elements.len() >= self.max_depth {
+            self.parse_error(format!("maximum nesting depth exceeded by <{}>", tag));
+            return false;
+        }
+
+        self.pop_text_node();
+
         let current = match self.stack_of_open_elements.last() {
             Some(n) => n.clone(),
-            None => window.document(),
+            None => self.window.borrow().document(),
         };
 
         let node = Rc::new(RefCell::new(self.create_element(tag, attributes)));
+        append_child(&current, node.clone());
+        self.window.borrow_mut().register_node(&node);
 
-        if current.borrow().first_child().is_some() {
-            let mut last_sibiling = current.borrow().first_child();
-            loop {
-                last_sibiling = match last_sibiling {
-                    Some(ref node) => {
-                        if node.borrow().next_sibling().is_some() {
-                            node.borrow().next_sibling()
-                        } else {
-                            break;
-                        }
-                    }
-                    None => unimplemented!("last_sibiling should be Some"),
-                };
+        self.stack_of_open_elements.push(node);
+        true
+    }
+
+    /// https://html.spec.whatwg.org/multipage/semantics.html#set-the-frozen-base-url
+    fn set_base_url(&mut self, attributes: &[Attribute]) {
+        if self.base_url_frozen {
+            return;
+        }
+
+        let href = match attributes.iter().find(|a| a.name() == "href") {
+            Some(a) => a.value(),
+            None => return,
+        };
+
+        let resolved = self.window.borrow().resolve_url(&href);
+        if let Ok(url) = resolved {
+            self.window.borrow_mut().set_base_url(url);
+            self.base_url_frozen = true;
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/links.html#rel-icon
+    /// rel属性に"icon"を含む<link>であれば、href属性のURLを文書のアイコンとして記録する。
+    /// "shortcut icon"も"icon"を含むため同じように扱う
+    fn set_icon_url(&mut self, attributes: &[Attribute]) {
+        let is_icon = attributes.iter().any(|a| {
+            a.name() == "rel"
+                && a
+                    .value()
+                    .split_ascii_whitespace()
+                    .any(|t| t.eq_ignore_ascii_case("icon"))
+        });
+        if !is_icon {
+            return;
+        }
+
+        let href = match attributes.iter().find(|a| a.name() == "href") {
+            Some(a) => a.value(),
+            None => return,
+        };
+
+        let resolved = self.window.borrow().resolve_url(&href);
+        if let Ok(url) = resolved {
+            self.window.borrow_mut().set_icon_url(url);
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inhead
+    /// スクリプトが有効な場合、<noscript>の中身は表示されないため、
+    /// </noscript>までのトークンを読み飛ばしてツリーに加えない
+    fn insert_noscript(&mut self, attributes: Vec<Attribute>) {
+        let inserted = self.insert_element("noscript", attributes);
+        if !self.window.borrow().scripting_enabled() {
+            return;
+        }
+
+        loop {
+            match self.t.next() {
+                Some(HtmlToken::EndTag { ref tag }) if tag == "noscript" => break,
+                Some(HtmlToken::Eof) | None => {
+                    self.parse_error(String::from("unexpected end of file in noscript"));
+                    break;
+                }
+                _ => {}
             }
+        }
+        if inserted {
+            self.pop_text_node();
+            self.pop_current_node(ElementKind::Noscript);
+        }
+    }
 
-            last_sibiling
-                .unwrap()
-                .borrow_mut()
-                .set_next_sibling(Some(node.clone()));
-            node.borrow_mut().set_previous_sibling(Rc::downgrade(
-                &current
-                        .borrow()
-                        .first_child()
-                        .expect("failed to get a first child"),
-            ))
-        } else {
-            current.borrow_mut().set_first_child(Some(node.clone()));
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inhead
+    /// <head>の中に置く要素を現在のノードに挿入する。<style>と<script>の場合はTextモードに入り、
+    /// 終了タグの後で今の挿入モードに戻る
+    fn insert_head_content(&mut self, tag: &str, attributes: &[Attribute]) {
+        match tag {
+            "style" | "script" => {
+                if self.insert_element(tag, attributes.to_vec()) {
+                    self.original_insertion_mode = self.mode;
+                    self.mode = InsertionMode::Text;
+                }
+            }
+            "base" => {
+                // <base>は空要素なので、挿入した直後にスタックから取り除く
+                if self.insert_element(tag, attributes.to_vec()) {
+                    self.pop_current_node(ElementKind::Base);
+                    self.set_base_url(attributes);
+                }
+            }
+            "link" => {
+                if self.insert_element(tag, attributes.to_vec()) {
+                    self.pop_current_node(ElementKind::Link);
+                    self.set_icon_url(attributes);
+                }
+            }
+            "noscript" => self.insert_noscript(attributes.to_vec()),
+            _ => unreachable!("<{}> is not head content", tag),
         }
+    }
 
-        current.borrow_mut().set_last_child(Rc::downgrade(&node));
-        node.borrow_mut().set_parent(Rc::downgrade(&current));
+    /// </body>や</html>で閉じた<body>をスタックに戻す。仕様では<body>はスタックに残り続けるので、
+    /// </body>の後に続く内容も<body>の中に挿入される
+    fn reopen_body(&mut self) {
+        if self.contain_in_stack(ElementKind::Body) {
+            return;
+        }
+        let body = self.window.borrow().body();
+        if let Some(body) = body {
+            self.pop_text_node();
+            self.stack_of_open_elements.push(body);
+        }
+    }
+
+    /// `node`をスタックから取り除く。現在のノードでなくてもよい
+    fn remove_from_stack(&mut self, node: &Rc<RefCell<Node>>) {
+        if let Some(i) = self
+            .stack_of_open_elements
+            .iter()
+            .rposition(|n| Rc::ptr_eq(n, node))
+        {
+            self.stack_of_open_elements.remove(i);
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-comment
+    /// 現在のノード(開いている要素がなければDocument)の最後の子としてコメントを挿入する
+    fn insert_comment(&mut self, data: &str) {
+        // コメントの後に続く文字は、コメントの前のテキストノードとは別のノードになる
+        self.pop_text_node();
+        let parent = match self.stack_of_open_elements.last() {
+            Some(n) => n.clone(),
+            None => self.window.borrow().document(),
+        };
+        let node = Rc::new(RefCell::new(Node::new(NodeKind::Comment(String::from(data)))));
+        append_child(&parent, node.clone());
+        self.window.borrow_mut().register_node(&node);
+    }
+
+    fn create_char(&self, c: char) -> Node {
+        let mut s = String::new();
+        s.push(c);
+        Node::new(NodeKind::Text(s))
+    }
+
+    fn insert_char(&mut self, c: char) {
+        let current = match self.stack_of_open_elements.last() {
+            Some(n) => n.clone(),
+            None => return,
+        };
+
+        // 現在参照しているノードがテキストノードの場合、そのノードに文字を追加する
+        if let NodeKind::Text(ref mut s) = current.borrow_mut().kind {
+            s.push(c);
+            return;
+        }
+
+        // 改行文字や空白文字のときは、テキストノードを追加しない
+        if c == '\n' || c == ' ' {
+            return;
+        }
+
+        let node = Rc::new(RefCell::new(self.create_char(c)));
+        append_child(&current, node.clone());
+        self.window.borrow_mut().register_node(&node);
 
         self.stack_of_open_elements.push(node);
     }
 
+    /// construction_treeと同じくツリーを構築する。strictモードの場合、トークナイザか
+    /// パーサのエラーが1つでもあれば、入力の中で最も手前の位置にあるエラーを
+    /// Error::InvalidHtmlとして返す。同じ位置の場合はトークナイザのエラーを返す
+    pub fn try_construction_tree(&mut self) -> Result<Rc<RefCell<Window>>, Error> {
+        let window = self.construction_tree();
+        if !self.strict {
+            return Ok(window);
+        }
+
+        let tokenizer_errors = self.t.parse_errors();
+        let first = tokenizer_errors
+            .iter()
+            .chain(self.parse_errors.iter())
+            .min_by_key(|e| e.source_position().offset());
+        match first {
+            Some(e) => Err(Error::InvalidHtml(format!(
+                "{} at line {}, column {}",
+                e.description(),
+                e.source_position().line(),
+                e.source_position().column()
+            ))),
+            None => Ok(window),
+        }
+    }
+
     pub fn construction_tree(&mut self) -> Rc<RefCell<Window>> {
         let mut token = self.t.next();
 
         while token.is_some() {
+            // どの挿入モードでも、コメントはその場所に挿入する
+            if let Some(HtmlToken::Comment(ref data)) = token {
+                self.insert_comment(data);
+                token = self.t.next();
+                continue;
+            }
+
             match self.mode {
                 InsertionMode::Initial => {
-                    // 本書では、DOCTYPEトークンをサポートしていないため、
-                    // <!doctype html>のようなトークンは文字トークンとして表される。
                     // 文字トークンは無視する
                     if let Some(HtmlToken::Char(_)) = token {
                         token = self.t.next();
                         continue;
                     }
 
+                    // 本書では、DOCTYPEの名前や識別子による互換モードの切り替えはサポートしない
+                    if let Some(HtmlToken::Doctype { .. }) = token {
+                        token = self.t.next();
+                        self.mode = InsertionMode::BeforeHtml;
+                        continue;
+                    }
+
                     self.mode = InsertionMode::BeforeHtml;
                     continue;
                 }
@@ -165,7 +442,7 @@ impl HtmlParser {
                         }
                         Some(HtmlToken::StartTag {
                             ref tag,
-                            self_closing,
+                            self_closing: _,
                             ref attributes,
                         }) => {
                             if tag == "html" {
@@ -175,8 +452,13 @@ impl HtmlParser {
                                 continue;
                             }
                         }
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error(String::from("unexpected DOCTYPE"));
+                            token = self.t.next();
+                            continue;
+                        }
                         Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
+                            return self.stop_parsing();
                         }
                         _ => {}
                     }
@@ -198,18 +480,27 @@ impl HtmlParser {
                             ref attributes,
                         }) => {
                             if tag == "head" {
-                                self.insert_element(tag, attributes.to_vec());
+                                if self.insert_element(tag, attributes.to_vec()) {
+                                    self.head_element = self.stack_of_open_elements.last().cloned();
+                                }
                                 self.mode = InsertionMode::InHead;
                                 token = self.t.next();
                                 continue;
                             }
                         }
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error(String::from("unexpected DOCTYPE"));
+                            token = self.t.next();
+                            continue;
+                        }
                         Some(HtmlToken::Eof) | None => {
-                            return  self.window.clone();
+                            return self.stop_parsing();
                         }
                         _ => {}
                     }
-                    self.insert_element("head", Vec::new());
+                    if self.insert_element("head", Vec::new()) {
+                        self.head_element = self.stack_of_open_elements.last().cloned();
+                    }
                     self.mode = InsertionMode::InHead;
                     continue;
                 }
@@ -226,10 +517,14 @@ impl HtmlParser {
                             self_closing: _, 
                             ref attributes,
                         }) => {
-                            if tag == "style" || tag == "script" {
-                                self.insert_element(tag, attributes.to_vec());
-                                self.original_insertion_mode = self.mode;
-                                self.mode = InsertionMode::Text;
+                            if tag == "head" {
+                                // 2つ目の<head>は無視する
+                                self.parse_error(String::from("unexpected start tag <head>"));
+                                token = self.t.next();
+                                continue;
+                            }
+                            if is_head_content(tag) {
+                                self.insert_head_content(tag, attributes);
                                 token = self.t.next();
                                 continue;
                             }
@@ -254,10 +549,25 @@ impl HtmlParser {
                                 self.pop_until(ElementKind::Head);
                                 continue;
                             }
+                            if tag == "noscript" {
+                                if self.contain_in_stack(ElementKind::Noscript) {
+                                    self.pop_until(ElementKind::Noscript);
+                                } else {
+                                    self.parse_error(String::from("unexpected end tag </noscript>"));
+                                }
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error(String::from("unexpected DOCTYPE"));
+                            token = self.t.next();
+                            continue;
                         }
                         Some(HtmlToken::Eof) | None => {
-                            return  self.window.clone();
+                            return self.stop_parsing();
                         }
+                        Some(HtmlToken::Comment(_)) => unreachable!("comments are already inserted"),
                     }
                     // <meta>や<title>などのサポートしていないタグは無視する
                     token = self.t.next();
@@ -282,9 +592,35 @@ impl HtmlParser {
                                 self.mode = InsertionMode::InBody;
                                 continue;
                             }
+                            if tag == "head" {
+                                self.parse_error(String::from("unexpected start tag <head>"));
+                                token = self.t.next();
+                                continue;
+                            }
+                            // </head>の後に現れた<head>の中に置くべき要素は、<head>を一時的に
+                            // スタックに戻してその中に挿入し、挿入した後すぐに<head>を取り除く。
+                            // 挿入モードはAfterHeadのままなので、<style>などの後もAfterHeadに戻る
+                            if is_head_content(tag) {
+                                if let Some(head) = self.head_element.clone() {
+                                    self.parse_error(format!(
+                                        "unexpected start tag <{}> after head",
+                                        tag
+                                    ));
+                                    self.stack_of_open_elements.push(head.clone());
+                                    self.insert_head_content(tag, attributes);
+                                    self.remove_from_stack(&head);
+                                    token = self.t.next();
+                                    continue;
+                                }
+                            }
+                        }
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error(String::from("unexpected DOCTYPE"));
+                            token = self.t.next();
+                            continue;
                         }
                         Some(HtmlToken::Eof) | None => {
-                            return  self.window.clone();
+                            return self.stop_parsing();
                         }
                         _ => {}
                 }
@@ -299,6 +635,36 @@ impl HtmlParser {
                         self_closing: _,
                         ref attributes,
                     }) => match tag.as_str() {
+                        "head" => {
+                            self.parse_error(String::from("unexpected start tag <head>"));
+                            token = self.t.next();
+                            continue;
+                        }
+                        // <head>の中に置くべき要素が<body>の中に現れた場合は、その場所に挿入する
+                        "style" | "script" => {
+                            if self.insert_element(tag, attributes.to_vec()) {
+                                self.original_insertion_mode = self.mode;
+                                self.mode = InsertionMode::Text;
+                            }
+                            token = self.t.next();
+                            continue;
+                        }
+                        "base" => {
+                            if self.insert_element(tag, attributes.to_vec()) {
+                                self.pop_current_node(ElementKind::Base);
+                                self.set_base_url(attributes);
+                            }
+                            token = self.t.next();
+                            continue;
+                        }
+                        "link" => {
+                            if self.insert_element(tag, attributes.to_vec()) {
+                                self.pop_current_node(ElementKind::Link);
+                                self.set_icon_url(attributes);
+                            }
+                            token = self.t.next();
+                            continue;
+                        }
                         "p" => {
                             self.insert_element(tag, attributes.to_vec());
                             token = self.t.next();
@@ -309,11 +675,59 @@ impl HtmlParser {
                             token = self.t.next();
                             continue;
                         }
+                        "center" => {
+                            // ブロック要素の開始タグは、開いている<p>を暗黙的に閉じる
+                            if self.contain_in_stack(ElementKind::P) {
+                                self.pop_until(ElementKind::P);
+                            }
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
                         "a" => {
                             self.insert_element(tag, attributes.to_vec());
                             token = self.t.next();
                             continue;
                         }
+                        "select" => {
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        "noscript" => {
+                            self.insert_noscript(attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        "form" | "textarea" => {
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        "input" => {
+                            // <input>は空要素なので、挿入した直後にスタックから取り除く
+                            self.insert_element(tag, attributes.to_vec());
+                            self.pop_current_node(ElementKind::Input);
+                            token = self.t.next();
+                            continue;
+                        }
+                        "img" => {
+                            self.insert_element(tag, attributes.to_vec());
+                            self.pop_current_node(ElementKind::Img);
+                            token = self.t.next();
+                            continue;
+                        }
+                        "optgroup" | "option" => {
+                            // 新しい<option>や<optgroup>は、開いている<option>を暗黙的に閉じる
+                            self.pop_text_node();
+                            self.pop_current_node(ElementKind::Option);
+                            if tag == "optgroup" {
+                                self.pop_current_node(ElementKind::Optgroup);
+                            }
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
                         _ => {
                             token = self.t.next();
                         }
@@ -325,39 +739,71 @@ impl HtmlParser {
                                 token = self.t.next();
                                 if !self.contain_in_stack(ElementKind::Body) {
                                     // パースの失敗。トークンを無視する
+                                    self.parse_error(String::from("unexpected end tag </body>"));
                                     continue;
                                 }
                                 self.pop_until(ElementKind::Body);
                                 continue;
                             }
                             "html" => {
-                                if self.pop_current_node(ElementKind::Body) {
-                                    self.mode = InsertionMode::AfterBody;
-                                    assert!(self.pop_current_node(ElementKind::Html));
-                                } else {
+                                if !self.contain_in_stack(ElementKind::Body) {
+                                    // パースの失敗。トークンを無視する
+                                    self.parse_error(String::from("unexpected end tag </html>"));
                                     token = self.t.next();
+                                    continue;
                                 }
+                                // </body>と同じく<body>までを閉じ、AfterBodyモードで</html>を
+                                // 処理し直してAfterAfterBodyモードに移る
+                                self.pop_until(ElementKind::Body);
+                                self.mode = InsertionMode::AfterBody;
                                 continue;
                             }
                             "p" => {
                                 let element_kind = ElementKind::from_str(tag)
                                     .expect("failed to convert string to ElementKind");
+                                // 対応する開始タグが無視された場合などは、終了タグも無視する
+                                if self.contain_in_stack(element_kind) {
+                                    self.pop_until(element_kind);
+                                } else {
+                                    self.parse_error(format!("unexpected end tag </{}>", tag));
+                                }
                                 token = self.t.next();
-                                self.pop_until(element_kind);
                                 continue;
                             }
                             "h1" | "h2" => {
                                 let element_kind = ElementKind::from_str(tag)
                                     .expect("failed to convert string to ElementKind");
+                                // 対応する開始タグが無視された場合などは、終了タグも無視する
+                                if self.contain_in_stack(element_kind) {
+                                    self.pop_until(element_kind);
+                                } else {
+                                    self.parse_error(format!("unexpected end tag </{}>", tag));
+                                }
                                 token = self.t.next();
-                                self.pop_until(element_kind);
                                 continue;
                             }
                             "a" => {
                                 let element_kind = ElementKind::from_str(tag)
                                     .expect("failed to convert string to ElementKind");
+                                // 対応する開始タグが無視された場合などは、終了タグも無視する
+                                if self.contain_in_stack(element_kind) {
+                                    self.pop_until(element_kind);
+                                } else {
+                                    self.parse_error(format!("unexpected end tag </{}>", tag));
+                                }
+                                token = self.t.next();
+                                continue;
+                            }
+                            "select" | "optgroup" | "option" | "noscript" | "form" | "textarea"
+                            | "center" => {
+                                let element_kind = ElementKind::from_str(tag)
+                                    .expect("failed to convert string to ElementKind");
+                                if self.contain_in_stack(element_kind) {
+                                    self.pop_until(element_kind);
+                                } else {
+                                    self.parse_error(format!("unexpected end tag </{}>", tag));
+                                }
                                 token = self.t.next();
-                                self.pop_until(element_kind);
                                 continue;
                             }
                             _ => {
@@ -365,20 +811,32 @@ impl HtmlParser {
                             }
                         }
                     }
+                    Some(HtmlToken::Doctype { .. }) => {
+                        self.parse_error(String::from("unexpected DOCTYPE"));
+                        token = self.t.next();
+                        continue;
+                    }
                     Some(HtmlToken::Eof) | None => {
-                        return self.window.clone();
+                        return self.stop_parsing();
                     }
                     Some(HtmlToken::Char(c)) => {
                         self.insert_char(c);
                         token = self.t.next();
                         continue;
                     }
+                    Some(HtmlToken::Comment(_)) => unreachable!("comments are already inserted"),
                 }
             }
             InsertionMode::Text => {
                 match token {
+                    Some(HtmlToken::Doctype { .. }) => {
+                        self.parse_error(String::from("unexpected DOCTYPE"));
+                        token = self.t.next();
+                        continue;
+                    }
                     Some(HtmlToken::Eof) | None => {
-                        return self.window.clone();
+                        self.parse_error(String::from("unexpected end of file in text"));
+                        return self.stop_parsing();
                     }
                     Some(HtmlToken::EndTag { ref tag }) => {
                         if tag == "style" {
@@ -406,7 +864,10 @@ impl HtmlParser {
             }
             InsertionMode::AfterBody => {
                 match token {
-                    Some(HtmlToken::Char(_c)) => {
+                    Some(HtmlToken::Char(c)) => {
+                        if c != ' ' && c != '\n' {
+                            self.parse_error(format!("unexpected character {:?} after body", c));
+                        }
                         token = self.t.next();
                         continue;
                     }
@@ -417,33 +878,570 @@ impl HtmlParser {
                             continue;
                         }
                     }
+                    Some(HtmlToken::Doctype { .. }) => {
+                        self.parse_error(String::from("unexpected DOCTYPE"));
+                        token = self.t.next();
+                        continue;
+                    }
                     Some(HtmlToken::Eof) | None => {
-                        return self.window.clone();
+                        return self.stop_parsing();
                     }
                     _ => {}
                 }
 
+                self.parse_error(String::from("unexpected token after body"));
+                self.reopen_body();
                 self.mode = InsertionMode::InBody;
             }
             InsertionMode::AfterAfterBody => {
                 match token {
-                    Some(HtmlToken::Char(_c)) => {
+                    Some(HtmlToken::Char(c)) => {
+                        if c != ' ' && c != '\n' {
+                            self.parse_error(format!("unexpected character {:?} after html", c));
+                        }
+                        token = self.t.next();
+                        continue;
+                    }
+                    Some(HtmlToken::Doctype { .. }) => {
+                        self.parse_error(String::from("unexpected DOCTYPE"));
                         token = self.t.next();
                         continue;
                     }
                     Some(HtmlToken::Eof) | None => {
-                        return self.window.clone();
+                        return self.stop_parsing();
                     }
                     _ => {}
                 }
 
                 // パースの失敗
+                self.parse_error(String::from("unexpected token after html"));
+                self.reopen_body();
                 self.mode = InsertionMode::InBody;
             }
         }
     }
 
-    self.window.clone()
+    self.stop_parsing()
 }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#stop-parsing
+    /// 文書の終わりに達したら、開いている要素を全てスタックから取り除いて閉じる。
+    /// どの挿入モードで終わっても、パースの後のスタックは常に空になる
+    fn stop_parsing(&mut self) -> Rc<RefCell<Window>> {
+        self.stack_of_open_elements.clear();
+        self.window.clone()
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inhead
+/// InHeadモードで<head>の中に挿入する要素
+fn is_head_content(tag: &str) -> bool {
+    matches!(tag, "base" | "link" | "noscript" | "script" | "style")
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::text_content;
+    use crate::renderer::dom::serializer::serialize;
+    use crate::renderer::dom::serializer::SerializeOptions;
+    use crate::alloc::string::ToString;
+    use crate::url::Url;
+    use alloc::string::String;
+    use alloc::vec;
+
+    #[test]
+    fn test_base_href() {
+        let html = "<html><head><base href=\"http://example.com/dir/\"></head><body><a href=\"page.html\">link</a></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construction_tree();
+
+        let url = window
+            .borrow()
+            .resolve_url("page.html")
+            .expect("failed to resolve url");
+        assert_eq!("example.com", url.host());
+        assert_eq!("dir/page.html", url.path());
+    }
+
+    #[test]
+    fn test_base_href_overrides_document_url() {
+        let html = "<html><head><base href=\"/other/\"></head><body><a href=\"page.html\">link</a></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let mut parser = HtmlParser::new(t);
+        parser.window.borrow_mut().set_base_url(
+            Url::new("http://example.com/dir/index.html".to_string())
+                .parse()
+                .expect("failed to parse url"),
+        );
+        let window = parser.construction_tree();
+
+        let url = window
+            .borrow()
+            .resolve_url("page.html")
+            .expect("failed to resolve url");
+        assert_eq!("example.com", url.host());
+        assert_eq!("other/page.html", url.path());
+    }
+
+    #[test]
+    fn test_only_first_base_takes_effect() {
+        let html = "<html><head><base href=\"http://first.com/\"><base href=\"http://second.com/\"></head><body></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construction_tree();
+
+        let base_url = window.borrow().base_url().expect("failed to get base url");
+        assert_eq!("first.com", base_url.host());
+    }
+
+    #[test]
+    fn test_icon_link() {
+        let html = "<html><head><base href=\"http://example.com/\"><link rel=\"stylesheet\" href=\"style.css\"><link rel=\"icon\" href=\"first.ico\"><link rel=\"shortcut icon\" href=\"favicon.ico\"><link rel=\"stylesheet\" href=\"other.css\"></head><body></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construction_tree();
+
+        let icon_url = window.borrow().icon_url().expect("failed to get icon url");
+        assert_eq!("example.com", icon_url.host());
+        assert_eq!("favicon.ico", icon_url.path());
+    }
+
+    #[test]
+    fn test_stylesheet_link_is_not_icon() {
+        let html = "<html><head><link rel=\"stylesheet\" href=\"http://example.com/style.css\"></head><body></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construction_tree();
+
+        assert_eq!(None, window.borrow().icon_url());
+    }
+
+    #[test]
+    fn test_noscript_scripting_disabled() {
+        let html = "<html><head></head><body><noscript><p>no js</p></noscript><p>after</p></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construction_tree();
+        let document = window.borrow().document();
+        let html = children(&document)[0].clone();
+        let body = children(&html)[1].clone();
+
+        let body_children = children(&body);
+        assert_eq!(2, body_children.len());
+        assert_eq!(Some(ElementKind::Noscript), body_children[0].borrow().element_kind());
+        assert_eq!(Some(ElementKind::P), body_children[1].borrow().element_kind());
+
+        // スクリプトが無効な場合、<noscript>の中身は通常のマークアップとしてパースされる
+        let noscript_children = children(&body_children[0]);
+        assert_eq!(1, noscript_children.len());
+        assert_eq!(Some(ElementKind::P), noscript_children[0].borrow().element_kind());
+        assert_eq!("no js", text(&children(&noscript_children[0])[0]));
+    }
+
+    #[test]
+    fn test_noscript_scripting_enabled() {
+        let html = "<html><head></head><body><noscript><p>no js</p></noscript><p>after</p></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let mut parser = HtmlParser::new(t);
+        parser.set_scripting_enabled(true);
+        let window = parser.construction_tree();
+        let document = window.borrow().document();
+        let html = children(&document)[0].clone();
+        let body = children(&html)[1].clone();
+
+        let body_children = children(&body);
+        assert_eq!(2, body_children.len());
+        assert_eq!(Some(ElementKind::Noscript), body_children[0].borrow().element_kind());
+        assert_eq!(Some(ElementKind::P), body_children[1].borrow().element_kind());
+        assert_eq!("after", text(&children(&body_children[1])[0]));
+
+        // スクリプトが有効な場合、<noscript>の中身は読み飛ばされる
+        assert!(children(&body_children[0]).is_empty());
+        assert!(parser.parse_errors().is_empty());
+    }
+
+    fn depth(node: &Rc<RefCell<Node>>) -> usize {
+        let mut max = 0;
+        let mut child = node.borrow().first_child();
+        while let Some(c) = child {
+            max = max.max(depth(&c));
+            child = c.borrow().next_sibling();
+        }
+        max + 1
+    }
+
+    #[test]
+    fn test_deeply_nested_elements() {
+        let mut html = String::new();
+        for _ in 0..10000 {
+            html.push_str("<p>");
+        }
+        html.push('x');
+        for _ in 0..10000 {
+            html.push_str("</p>");
+        }
+
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construction_tree();
+
+        // document + 上限までの要素 + テキストノード
+        assert!(depth(&window.borrow().document()) <= DEFAULT_MAX_DEPTH + 2);
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let html = "<html><head></head><body><p><a><p>x</p></a></p></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let mut parser = HtmlParser::new(t);
+        parser.set_max_depth(4);
+        let window = parser.construction_tree();
+
+        // html > body > p > a までしか開かれず、内側の<p>は無視されてテキストは<a>の子になる
+        let document = window.borrow().document();
+        assert_eq!(6, depth(&document));
+    }
+
+    #[test]
+    fn test_max_depth_skips_structural_elements() {
+        // <head>や<body>が挿入されなくても、それらを閉じる処理でパニックしない
+        let html = "<html><head></head><body><p>a</p></body></html>".to_string();
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html));
+        parser.set_max_depth(1);
+        let window = parser.construction_tree();
+        assert_eq!("<html>a</html>", serialize_document(&window));
+
+        // 挿入されなかった<style>のためにTextモードに入らない
+        let html = "<head><style>x</style><base href=\"http://example.com/\"></head><body>b</body>";
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html.to_string()));
+        parser.set_max_depth(2);
+        let window = parser.construction_tree();
+        assert_eq!("<html><head></head><body>b</body></html>", serialize_document(&window));
+        assert_eq!(None, window.borrow().base_url());
+    }
+
+    #[test]
+    fn test_deeply_nested_style_and_script() {
+        // 入れ子の深さの上限を超えた<style>と<script>は挿入されず、終了タグでパニックしない
+        for tag in ["p", "a", "h1"] {
+            let mut html = String::from("<html><head></head><body>");
+            html.push_str(&format!("<{}>", tag).repeat(600));
+            html.push_str("<style>x</style><script>y</script>z</body></html>");
+            let mut parser = HtmlParser::new(HtmlTokenizer::new(html));
+            let window = parser.construction_tree();
+
+            let body = window.borrow().body().expect("failed to find body");
+            let body_text = text_content(&body);
+            assert_eq!("xyz", body_text);
+            assert!(parser
+                .parse_errors()
+                .iter()
+                .any(|e| e.description() == "maximum nesting depth exceeded by <style>"));
+        }
+    }
+
+    fn children(node: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+        let mut children = Vec::new();
+        let mut child = node.borrow().first_child();
+        while let Some(c) = child {
+            child = c.borrow().next_sibling();
+            children.push(c);
+        }
+        children
+    }
+
+    fn text(node: &Rc<RefCell<Node>>) -> String {
+        match node.borrow().kind() {
+            NodeKind::Text(s) => s,
+            _ => panic!("node should be a text node"),
+        }
+    }
+
+    fn comment(node: &Rc<RefCell<Node>>) -> String {
+        match node.borrow().kind() {
+            NodeKind::Comment(s) => s,
+            _ => panic!("node should be a comment node"),
+        }
+    }
+
+    #[test]
+    fn test_select_and_options() {
+        let html = "<html><head></head><body><select><option>A<option>B</option><optgroup><option>C<optgroup><option>D</select></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construction_tree();
+        let document = window.borrow().document();
+
+        let html = children(&document)[0].clone();
+        let body = children(&html)[1].clone();
+        let select = children(&body)[0].clone();
+        assert_eq!(Some(ElementKind::Select), select.borrow().element_kind());
+
+        let options = children(&select);
+        let kinds: Vec<Option<ElementKind>> =
+            options.iter().map(|n| n.borrow().element_kind()).collect();
+        assert_eq!(
+            vec![
+                Some(ElementKind::Option),
+                Some(ElementKind::Option),
+                Some(ElementKind::Optgroup),
+                Some(ElementKind::Optgroup),
+            ],
+            kinds
+        );
+        assert_eq!("A", text(&children(&options[0])[0]));
+        assert_eq!("B", text(&children(&options[1])[0]));
+
+        let grouped = children(&options[2]);
+        assert_eq!(1, grouped.len());
+        assert_eq!(Some(ElementKind::Option), grouped[0].borrow().element_kind());
+        assert_eq!("C", text(&children(&grouped[0])[0]));
+        assert_eq!("D", text(&children(&children(&options[3])[0])[0]));
+    }
+
+    #[test]
+    fn test_no_parse_errors() {
+        let html = "<!DOCTYPE html>\n<html><head></head><body><p>a</p></body></html>\n".to_string();
+        let t = HtmlTokenizer::new(html);
+        let mut parser = HtmlParser::new(t);
+        parser.construction_tree();
+        assert_eq!(Vec::<ParseError>::new(), parser.parse_errors());
+    }
+
+    #[test]
+    fn test_stray_end_tag() {
+        let html = "<html><head></head><body></p><p>a</p></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let mut parser = HtmlParser::new(t);
+        parser.construction_tree();
+
+        let errors = parser.parse_errors();
+        assert_eq!(1, errors.len());
+        assert_eq!("unexpected end tag </p>", errors[0].description());
+        assert_eq!(29, errors[0].position());
+        assert_eq!(1, errors[0].source_position().line());
+        assert_eq!(26, errors[0].source_position().column());
+    }
+
+    #[test]
+    fn test_unexpected_doctype() {
+        let html = "<html><head></head><body><!DOCTYPE html><p>a</p></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let mut parser = HtmlParser::new(t);
+        let window = parser.construction_tree();
+
+        let errors = parser.parse_errors();
+        assert_eq!(1, errors.len());
+        assert_eq!("unexpected DOCTYPE", errors[0].description());
+
+        // DOCTYPEは無視され、ツリーには現れない
+        let html = children(&window.borrow().document())[0].clone();
+        let body = children(&html)[1].clone();
+        let body_children = children(&body);
+        assert_eq!(1, body_children.len());
+        assert_eq!(Some(ElementKind::P), body_children[0].borrow().element_kind());
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let html = "<html><head></head><body></p><p>a</p></body></html>";
+
+        // 既定のlenientモードでは、エラーがあってもツリーを返す
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html.to_string()));
+        assert!(parser.try_construction_tree().is_ok());
+        assert_eq!(1, parser.parse_errors().len());
+
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html.to_string()));
+        parser.set_strict(true);
+        match parser.try_construction_tree() {
+            Err(Error::InvalidHtml(message)) => {
+                assert_eq!("unexpected end tag </p> at line 1, column 26", message)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(
+            "<html><head></head><body><p>a</p></body></html>".to_string(),
+        ));
+        parser.set_strict(true);
+        assert!(parser.try_construction_tree().is_ok());
+    }
+
+    fn serialize_document(window: &Rc<RefCell<Window>>) -> String {
+        serialize(&window.borrow().document(), &SerializeOptions::default())
+    }
+
+    #[test]
+    fn test_duplicate_head() {
+        let html = "<html><head><link rel=icon href=a.ico></head><head><style>x</style></head><body><p>a</p></body></html>".to_string();
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html));
+        let window = parser.construction_tree();
+
+        // 2つ目の<head>は無視され、その中の要素は最初の<head>に入る
+        assert_eq!(
+            "<html><head><link href=\"a.ico\" rel=\"icon\"><style>x</style></head><body><p>a</p></body></html>",
+            serialize_document(&window)
+        );
+        let errors = parser.parse_errors();
+        assert_eq!(2, errors.len());
+        assert_eq!("unexpected start tag <head>", errors[0].description());
+        assert_eq!("unexpected start tag <style> after head", errors[1].description());
+    }
+
+    #[test]
+    fn test_head_elements_after_head() {
+        let html = "<html><head></head><style>x</style>hello<body><p>a</p></body></html>";
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html.to_string()));
+        let window = parser.construction_tree();
+
+        // <style>は<head>に入り、その後の文字はAfterHeadモードで<body>を補って挿入される
+        assert_eq!(
+            "<html><head><style>x</style></head><body>hello<p>a</p></body></html>",
+            serialize_document(&window)
+        );
+        assert_eq!(
+            vec!["unexpected start tag <style> after head"],
+            parser
+                .parse_errors()
+                .iter()
+                .map(|e| e.description())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_head_elements_in_body() {
+        let html = "<html><head><base href=http://example.com/></head><body><p>a</p><head><style>b</style><link rel=icon href=i.ico></body></html>".to_string();
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html));
+        let window = parser.construction_tree();
+
+        // <body>の中の<head>は無視し、<head>に置くべき要素は現れた場所に挿入する
+        assert_eq!(
+            "<html><head><base href=\"http://example.com/\"></head><body><p>a</p><style>b</style><link href=\"i.ico\" rel=\"icon\"></body></html>",
+            serialize_document(&window)
+        );
+        assert_eq!(
+            Some("i.ico".to_string()),
+            window.borrow().icon_url().map(|u| u.path())
+        );
+        let errors = parser.parse_errors();
+        assert_eq!(1, errors.len());
+        assert_eq!("unexpected start tag <head>", errors[0].description());
+    }
+
+    #[test]
+    fn test_stop_parsing_at_eof() {
+        for html in [
+            "<html><head></head><body><p>a<a href=x>b",
+            "<html><head><style>a",
+            "<html><head>",
+            "<html><head></head><body><form><select><option>a",
+        ] {
+            let mut parser = HtmlParser::new(HtmlTokenizer::new(html.to_string()));
+            parser.construction_tree();
+            assert!(parser.stack_of_open_elements.is_empty(), "{}", html);
+        }
+
+        // 閉じられていない要素も、文書の終わりまでの内容を持ったままツリーに残る
+        let html = "<html><head></head><body><p>a<a href=x>b";
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html.to_string()));
+        let window = parser.construction_tree();
+        assert_eq!(
+            "<html><head></head><body><p>a<a href=\"x\">b</a></p></body></html>",
+            serialize_document(&window)
+        );
+    }
+
+    #[test]
+    fn test_conditional_comment() {
+        let html = "<!-- top --><html><head></head><body><p>a<!--[if IE]><p class=\"ie\">old</p><![endif]-->b</p></body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construction_tree();
+        let document = window.borrow().document();
+        let p = children(&children(&children(&document)[1])[1])[0].clone();
+
+        // 条件付きコメントの中身は要素にならず、1つのコメントノードに収まる
+        let p_children = children(&p);
+        assert_eq!(3, p_children.len());
+        assert_eq!("a", text(&p_children[0]));
+        assert_eq!("[if IE]><p class=\"ie\">old</p><![endif]", comment(&p_children[1]));
+        assert_eq!("b", text(&p_children[2]));
+        assert_eq!(" top ", comment(&children(&document)[0]));
+        assert_eq!(
+            "<!-- top --><html><head></head><body><p>a<!--[if IE]><p class=\"ie\">old</p><![endif]-->b</p></body></html>",
+            serialize_document(&window)
+        );
+    }
+
+    #[test]
+    fn test_html_end_tag_in_body() {
+        let html = "<html><head></head><body><p>a<a href=x>b</html>";
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html.to_string()));
+        let window = parser.construction_tree();
+        assert_eq!(InsertionMode::AfterAfterBody, parser.mode);
+        assert_eq!(
+            "<html><head></head><body><p>a<a href=\"x\">b</a></p></body></html>",
+            serialize_document(&window)
+        );
+        assert!(parser.parse_errors().is_empty());
+
+        // </html>の後の内容も<body>の中に入る
+        let html = "<html><head></head><body><p>a</html><p>b";
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html.to_string()));
+        let window = parser.construction_tree();
+        assert_eq!(
+            "<html><head></head><body><p>a</p><p>b</p></body></html>",
+            serialize_document(&window)
+        );
+        assert_eq!(1, parser.parse_errors().len());
+        assert_eq!("unexpected token after html", parser.parse_errors()[0].description());
+    }
+
+    #[test]
+    fn test_strict_mode_with_tokenizer_errors() {
+        let html = "<html><head></head><body><p title=\"abcdefgh\">a</p></body></html>";
+        let tokenizer = || {
+            let mut t = HtmlTokenizer::new(html.to_string());
+            t.set_max_attribute_value_length(4);
+            t
+        };
+
+        // lenientモードでは、切り詰めた値でツリーを返す
+        let mut parser = HtmlParser::new(tokenizer());
+        let window = parser.try_construction_tree().expect("failed to construct a tree");
+        assert_eq!(
+            "<html><head></head><body><p title=\"abcd\">a</p></body></html>",
+            serialize_document(&window)
+        );
+
+        let mut parser = HtmlParser::new(tokenizer());
+        parser.set_strict(true);
+        match parser.try_construction_tree() {
+            Err(Error::InvalidHtml(message)) => assert_eq!(
+                "attribute value exceeds 4 bytes and is truncated at line 1, column 41",
+                message
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_strict_mode_earliest_error() {
+        let html = "<html><head></head><body></p><p title=\"abcdef\">a</p></body></html>";
+        let mut t = HtmlTokenizer::new(html.to_string());
+        t.set_max_attribute_value_length(4);
+        let mut parser = HtmlParser::new(t);
+        parser.set_strict(true);
+        match parser.try_construction_tree() {
+            Err(Error::InvalidHtml(message)) => {
+                assert_eq!("unexpected end tag </p> at line 1, column 26", message)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(1, parser.parse_errors().len());
+    }
+
+    #[test]
+    fn test_center() {
+        let html = "<html><head></head><body><p>a<center>b<a href=x>c</a></center>d</body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construction_tree();
+        assert_eq!(
+            "<html><head></head><body><p>a</p><center>b<a href=\"x\">c</a></center>d</body></html>",
+            serialize_document(&window)
+        );
+    }
+}
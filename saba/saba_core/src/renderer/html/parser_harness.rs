@@ -0,0 +1,106 @@
+//! パーサのテストを表形式でまとめて書くための仕組み。
+//! 各ケースは入力のHTMLと、期待するツリーをdump_treeの形式で1行ずつ書いたものの組で表す
+
+use crate::renderer::dom::serializer::dump_tree;
+use crate::renderer::html::parser::HtmlParser;
+use crate::renderer::html::token::HtmlTokenizer;
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// 全てのケースを実行し、期待と異なったケースは両方のツリーを並べて全てまとめて報告する
+pub fn run_cases(cases: &[(&str, &[&str])]) {
+    let mut failures = Vec::new();
+    for (i, (input, expected)) in cases.iter().enumerate() {
+        let window = HtmlParser::new(HtmlTokenizer::new(input.to_string())).construction_tree();
+        let actual = dump_tree(&window.borrow().document());
+        let expected = expected.join("\n");
+        if actual != expected {
+            failures.push(format!(
+                "case {} {:?}\n--- expected\n{}\n--- actual\n{}",
+                i, input, expected, actual
+            ));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} parser cases failed:\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn test_parser_cases() {
+    run_cases(&[
+        // 省略されたタグの補完
+        ("", &[]),
+        ("<p>a", &[
+            "<html>",
+            "  <head>",
+            "  <body>",
+            "    <p>",
+            "      \"a\"",
+        ]),
+        ("<!DOCTYPE html><title>x</title><p>a</p>", &[
+            "<html>",
+            "  <head>",
+            "  <body>",
+            "    <p>",
+            "      \"a\"",
+        ]),
+        ("<html><body><p>a</p></body></html>", &[
+            "<html>",
+            "  <head>",
+            "  <body>",
+            "    <p>",
+            "      \"a\"",
+        ]),
+        // 空要素
+        ("<body><input name=q><img src=a.png>b", &[
+            "<html>",
+            "  <head>",
+            "  <body>",
+            "    <input>",
+            "      name=\"q\"",
+            "    <img>",
+            "      src=\"a.png\"",
+            "    \"b\"",
+        ]),
+        ("<head><base href=http://example.com/><link rel=icon href=a.ico></head>", &[
+            "<html>",
+            "  <head>",
+            "    <base>",
+            "      href=\"http://example.com/\"",
+            "    <link>",
+            "      href=\"a.ico\"",
+            "      rel=\"icon\"",
+        ]),
+        // 入れ子
+        ("<body><p>a<a href=x>b</a>c</p><h1>d</h1>", &[
+            "<html>",
+            "  <head>",
+            "  <body>",
+            "    <p>",
+            "      \"a\"",
+            "      <a>",
+            "        href=\"x\"",
+            "        \"b\"",
+            "      \"c\"",
+            "    <h1>",
+            "      \"d\"",
+        ]),
+        ("<body><form><select><option>a<option>b</select></form>", &[
+            "<html>",
+            "  <head>",
+            "  <body>",
+            "    <form>",
+            "      <select>",
+            "        <option>",
+            "          \"a\"",
+            "        <option>",
+            "          \"b\"",
+        ]),
+    ]);
+}
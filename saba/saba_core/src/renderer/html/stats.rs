@@ -0,0 +1,94 @@
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::Window;
+use crate::renderer::html::parser::HtmlParser;
+use crate::renderer::html::token::HtmlTokenizer;
+use alloc::rc::Rc;
+use alloc::string::String;
+use core::cell::RefCell;
+
+/// 大きなページのパース性能を調べるための統計情報
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseStats {
+    /// トークナイザが出力したトークンの数
+    pub token_count: usize,
+    /// パーサが作成したノードの数(Documentノードは含まない)
+    pub node_count: usize,
+    /// トークン化にかかった時間
+    pub tokenize_ticks: u64,
+    /// ツリー構築にかかった時間
+    pub tree_construction_ticks: u64,
+}
+
+/// HTMLをパースし、DOMツリーと統計情報を返す。
+/// `now`には、wasabi OSのタイマーなどプラットフォームの時刻を返す関数を渡す。
+///
+/// パーサはトークナイザから1つずつトークンを受け取りながらツリーを構築するため、
+/// トークン化の時間はトークナイザ単体を最後まで実行して計測し、ツリー構築の時間は
+/// パース全体の時間からトークン化の時間を差し引いた値とする
+pub fn parse_with_stats(html: String, now: fn() -> u64) -> (Rc<RefCell<Window>>, ParseStats) {
+    let tokenizer = HtmlTokenizer::new(html);
+
+    let start = now();
+    let token_count = tokenizer.clone().count();
+    let tokenize_ticks = now().saturating_sub(start);
+
+    let start = now();
+    let window = HtmlParser::new(tokenizer).construction_tree();
+    let parse_ticks = now().saturating_sub(start);
+
+    let node_count = count_nodes(&window.borrow().document()) - 1;
+
+    let stats = ParseStats {
+        token_count,
+        node_count,
+        tokenize_ticks,
+        tree_construction_ticks: parse_ticks.saturating_sub(tokenize_ticks),
+    };
+
+    (window, stats)
+}
+
+fn count_nodes(node: &Rc<RefCell<Node>>) -> usize {
+    let mut count = 1;
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        count += count_nodes(&c);
+        child = c.borrow().next_sibling();
+    }
+
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use core::sync::atomic::AtomicU64;
+    use core::sync::atomic::Ordering;
+
+    static TICKS: AtomicU64 = AtomicU64::new(0);
+
+    fn fake_now() -> u64 {
+        TICKS.fetch_add(1, Ordering::Relaxed)
+    }
+
+    #[test]
+    fn test_parse_with_stats() {
+        let html = "<html><head></head><body><p>hi</p></body></html>".to_string();
+        let (window, stats) = parse_with_stats(html, fake_now);
+
+        // <html>, <head>, </head>, <body>, <p>, 'h', 'i', </p>, </body>, </html>
+        assert_eq!(10, stats.token_count);
+        // html, head, body, p, "hi"
+        assert_eq!(5, stats.node_count);
+        assert!(window.borrow().document().borrow().first_child().is_some());
+    }
+
+    #[test]
+    fn test_parse_with_stats_empty() {
+        let (_window, stats) = parse_with_stats("".to_string(), fake_now);
+        assert_eq!(0, stats.token_count);
+        assert_eq!(0, stats.node_count);
+    }
+}
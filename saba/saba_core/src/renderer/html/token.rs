@@ -1,7 +1,15 @@
 use crate::renderer::html::attribute::Attribute;
+use crate::renderer::html::entity::lookup_named_character_reference;
+use crate::renderer::html::parser::ParseError;
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 
+/// タグ名と属性名の長さ(UTF-8のバイト数)の既定の上限
+pub const DEFAULT_MAX_NAME_LENGTH: usize = 1024;
+/// 属性値の長さ(UTF-8のバイト数)の既定の上限。data: URLなどの長い値も収まるようにしている
+pub const DEFAULT_MAX_ATTRIBUTE_VALUE_LENGTH: usize = 1024 * 1024;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HtmlToken {
     // 開始タグ
@@ -14,12 +22,59 @@ pub enum HtmlToken {
     EndTag {
         tag: String,
     },
+    // DOCTYPE
+    Doctype {
+        name: String,
+    },
     // 文字
     Char(char),
+    // コメント
+    Comment(String),
     // ファイルの終了(End of File)
     Eof,
 }
 
+/// 入力中の位置。offsetは改行を正規化した後の入力でのバイト単位、lineとcolumnは1から数え、
+/// columnは文字単位
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SourcePosition {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl SourcePosition {
+    fn start() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    fn advance(&mut self, c: char) {
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum State {
     /// https://html.spec.whatwg.org/multipage/parsing.html#data-state
@@ -48,6 +103,24 @@ pub enum State {
     AfterAttributeValueQuoted,
     /// https://html.spec.whatwg.org/multipage/parsing.html#self-closing-start-tag-state
     SelfClosingStartTag,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#before-doctype-name-state
+    BeforeDoctypeName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-name-state
+    DoctypeName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-name-state
+    AfterDoctypeName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#bogus-comment-state
+    BogusComment,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-start-state
+    CommentStart,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-start-dash-state
+    CommentStartDash,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-state
+    Comment,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-end-dash-state
+    CommentEndDash,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-end-state
+    CommentEnd,
     /// https://html.spec.whatwg.org/multipage/parsing.html#script-data-state
     ScriptData,
     /// https://html.spec.whatwg.org/multipage/parsing.html#script-data-less-than-sign-state
@@ -60,6 +133,18 @@ pub enum State {
     TemporaryBuffer,
 }
 
+/// https://html.spec.whatwg.org/multipage/parsing.html#preprocessing-the-input-stream
+/// CRLFと単独のCRをLFに置き換える。HTMLではXMLと違い、属性値の中のタブや改行を
+/// 空白に置き換えないので、改行の正規化はこれだけになる
+fn normalize_newlines(html: &str) -> String {
+    html.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// タグの中で区切りとして扱う空白文字。タブ、LF、FF、空白
+fn is_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | ' ')
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HtmlTokenizer {
     state: State,
@@ -68,6 +153,23 @@ pub struct HtmlTokenizer {
     latest_token: Option<HtmlToken>,
     input: Vec<char>,
     buf: String,
+    /// 直前に返したトークンの開始位置
+    token_start: SourcePosition,
+    /// 行と列を数え終えた入力の位置(文字単位)。トークンの開始位置は前に進む一方なので、
+    /// 続きから数えれば入力全体を一度走査するだけで済む
+    scanned: usize,
+    scanned_position: SourcePosition,
+    /// 悪意のある入力で際限なくメモリを確保しないように、上限を超えた部分は切り捨てる
+    max_name_length: usize,
+    max_attribute_value_length: usize,
+    /// 今読んでいるタグ名・属性名・属性値の長さと、それを既に切り捨てたかどうか。
+    /// エラーは切り捨てるたびではなく、1つの名前や値につき1回だけ記録する
+    current_length: usize,
+    truncated: bool,
+    /// 直前に追加したのが属性名か属性値か
+    appending_attribute_name: bool,
+    /// トークン化の途中で見つかったエラー
+    parse_errors: Vec<ParseError>,
 }
 
 impl HtmlTokenizer {
@@ -77,22 +179,95 @@ impl HtmlTokenizer {
             pos: 0, 
             reconsume: false, 
             latest_token: None, 
-            input: html.chars().collect(), 
+            input: normalize_newlines(&html).chars().collect(), 
             buf: String::new(),
+            token_start: SourcePosition::start(),
+            scanned: 0,
+            scanned_position: SourcePosition::start(),
+            max_name_length: DEFAULT_MAX_NAME_LENGTH,
+            max_attribute_value_length: DEFAULT_MAX_ATTRIBUTE_VALUE_LENGTH,
+            current_length: 0,
+            truncated: false,
+            appending_attribute_name: false,
+            parse_errors: Vec::new(),
         }
     }
 
+    pub fn set_max_name_length(&mut self, max_name_length: usize) {
+        self.max_name_length = max_name_length;
+    }
+
+    pub fn set_max_attribute_value_length(&mut self, max_attribute_value_length: usize) {
+        self.max_attribute_value_length = max_attribute_value_length;
+    }
+
+    pub fn parse_errors(&self) -> Vec<ParseError> {
+        self.parse_errors.clone()
+    }
+
+    fn reset_length(&mut self) {
+        self.current_length = 0;
+        self.truncated = false;
+    }
+
+    /// 今読んでいる名前や値に`c`を追加すると`max`を超える場合はfalseを返し、
+    /// 最初の1回だけエラーを記録する
+    fn can_append(&mut self, c: char, max: usize, what: &str) -> bool {
+        if self.current_length + c.len_utf8() <= max {
+            self.current_length += c.len_utf8();
+            return true;
+        }
+        if !self.truncated {
+            self.truncated = true;
+            let position = self.pos;
+            let source_position = self.source_position(position);
+            self.parse_errors.push(ParseError::new(
+                format!("{} exceeds {} bytes and is truncated", what, max),
+                position,
+                source_position,
+            ));
+        }
+        false
+    }
+
+    /// 次に読む文字の位置。パースエラーのおおよその位置として使う
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// 直前にnext()が返したトークンの、入力中の開始位置
+    pub fn token_position(&self) -> SourcePosition {
+        self.token_start
+    }
+
+    /// 文字単位の位置`index`を、行と列を含むSourcePositionに変換する
+    fn source_position(&mut self, index: usize) -> SourcePosition {
+        if index < self.scanned {
+            self.scanned = 0;
+            self.scanned_position = SourcePosition::start();
+        }
+        let end = index.min(self.input.len());
+        for i in self.scanned..end {
+            self.scanned_position.advance(self.input[i]);
+        }
+        self.scanned = end;
+        self.scanned_position
+    }
+
     fn is_eof(&self) -> bool {
         self.pos > self.input.len()
     }
 
+    /// 入力の終わりを越えて読んだ場合は位置だけを進めて'\0'を返す。各状態はis_eofで
+    /// 入力の終わりを判定する
     fn consume_next_input(&mut self) -> char {
-        let c = self.input[self.pos];
+        let c = self.input.get(self.pos).copied().unwrap_or('\0');
         self.pos += 1;
         c
     }
 
     fn create_tag(&mut self, start_tag_token: bool) {
+        self.reset_length();
         if start_tag_token {
             self.latest_token = Some(HtmlToken::StartTag {
                 tag: String::new(), 
@@ -106,6 +281,50 @@ impl HtmlTokenizer {
         }
     }
 
+    /// https://html.spec.whatwg.org/multipage/parsing.html#markup-declaration-open-state
+    /// 次の入力が大文字小文字を区別せずに`s`と一致すれば、それを消費してtrueを返す
+    fn consume_if_ascii_case_insensitive_match(&mut self, s: &str) -> bool {
+        let len = s.chars().count();
+        if self.pos + len > self.input.len() {
+            return false;
+        }
+
+        let matched = self.input[self.pos..self.pos + len]
+            .iter()
+            .zip(s.chars())
+            .all(|(a, b)| a.eq_ignore_ascii_case(&b));
+        if matched {
+            self.pos += len;
+        }
+        matched
+    }
+
+    fn create_doctype(&mut self) {
+        self.latest_token = Some(HtmlToken::Doctype {
+            name: String::new(),
+        });
+    }
+
+    fn create_comment(&mut self) {
+        self.latest_token = Some(HtmlToken::Comment(String::new()));
+    }
+
+    fn append_comment(&mut self, c: char) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(HtmlToken::Comment(ref mut data)) = self.latest_token.as_mut() {
+            data.push(c);
+        }
+    }
+
+    fn append_doctype_name(&mut self, c: char) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(HtmlToken::Doctype { ref mut name }) = self.latest_token.as_mut() {
+            name.push(c);
+        }
+    }
+
     fn reconsume_input(&mut self) -> char {
         self.reconsume = false;
         self.input[self.pos - 1]
@@ -114,6 +333,10 @@ impl HtmlTokenizer {
     fn append_tag_name(&mut self, c: char) {
         assert!(self.latest_token.is_some());
 
+        if !self.can_append(c, self.max_name_length, "tag name") {
+            return;
+        }
+
         if let Some(t) = self.latest_token.as_mut() {
             match t {
                 HtmlToken::StartTag {
@@ -139,6 +362,8 @@ impl HtmlTokenizer {
 
     fn start_new_attribute(&mut self) {
         assert!(self.latest_token.is_some());
+        self.reset_length();
+        self.appending_attribute_name = true;
 
         if let Some(t) = self.latest_token.as_mut() {
             match t {
@@ -157,6 +382,20 @@ impl HtmlTokenizer {
     fn append_attribute(&mut self, c: char, is_name: bool) {
         assert!(self.latest_token.is_some());
 
+        // 属性名の後に属性値を読み始めたら、長さを数え直す
+        if is_name != self.appending_attribute_name {
+            self.appending_attribute_name = is_name;
+            self.reset_length();
+        }
+        let appendable = if is_name {
+            self.can_append(c, self.max_name_length, "attribute name")
+        } else {
+            self.can_append(c, self.max_attribute_value_length, "attribute value")
+        };
+        if !appendable {
+            return;
+        }
+
         if let Some(t) = self.latest_token.as_mut() {
             match t {
                 HtmlToken::StartTag {
@@ -174,6 +413,56 @@ impl HtmlTokenizer {
         }
     }
 
+    /// 読み込み中の属性値を囲む引用符を記録する
+    fn set_attribute_quote(&mut self, quote: char) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(HtmlToken::StartTag {
+            ref mut attributes, ..
+        }) = self.latest_token
+        {
+            if let Some(attribute) = attributes.last_mut() {
+                attribute.set_quote(quote);
+            }
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#attribute-value-(double-quoted)-state
+    /// 属性値の中の文字参照はデコードしてから追加する
+    fn append_attribute_value(&mut self, c: char) {
+        if c == '&' {
+            if let Some(decoded) = self.consume_named_character_reference() {
+                self.append_attribute(decoded, /*is_name*/ false);
+                return;
+            }
+        }
+        self.append_attribute(c, /*is_name*/ false);
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#named-character-reference-state
+    /// `&`の直後から`;`までを名前として読み、既知の名前であれば対応する文字を返す。
+    /// 未知の名前の場合は入力を消費せずにNoneを返すので、`&`以降はそのまま文字として扱われる
+    fn consume_named_character_reference(&mut self) -> Option<char> {
+        let mut name = String::new();
+        let mut i = self.pos;
+
+        while i < self.input.len() {
+            let c = self.input[i];
+            if c == ';' {
+                let decoded = lookup_named_character_reference(&name)?;
+                self.pos = i + 1;
+                return Some(decoded);
+            }
+            if !c.is_ascii_alphanumeric() {
+                return None;
+            }
+            name.push(c);
+            i += 1;
+        }
+
+        None
+    }
+
     fn set_self_closing_flag(&mut self) {
         assert!(self.latest_token.is_some());
 
@@ -198,7 +487,22 @@ impl Iterator for HtmlTokenizer {
             return  None;
         }
 
+        // トークンは直前のトークンの直後から始まる
+        let start = if self.reconsume { self.pos - 1 } else { self.pos };
+        self.token_start = self.source_position(start);
+
         loop {
+            // 入力の終わりを越えた場合、作りかけのコメントやDOCTYPEはそこで閉じて返し、
+            // タグは捨てる
+            if self.is_eof() {
+                return match self.latest_token {
+                    Some(HtmlToken::Comment(_)) | Some(HtmlToken::Doctype { .. }) => {
+                        self.take_latest_token()
+                    }
+                    _ => Some(HtmlToken::Eof),
+                };
+            }
+
             let c = match self.reconsume {
                 true => self.reconsume_input(),
                 false => self.consume_next_input(),
@@ -211,6 +515,12 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    if c == '&' {
+                        if let Some(decoded) = self.consume_named_character_reference() {
+                            return Some(HtmlToken::Char(decoded));
+                        }
+                    }
+
                     if self.is_eof() {
                         return Some(HtmlToken::Eof);
                     }
@@ -223,6 +533,26 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    // https://html.spec.whatwg.org/multipage/parsing.html#markup-declaration-open-state
+                    // CDATAセクションはサポートしない
+                    if c == '!' {
+                        if self.consume_if_ascii_case_insensitive_match("--") {
+                            self.state = State::CommentStart;
+                            self.create_comment();
+                            continue;
+                        }
+                        if self.consume_if_ascii_case_insensitive_match("doctype") {
+                            self.state = State::BeforeDoctypeName;
+                            self.create_doctype();
+                            continue;
+                        }
+                        // `<!`の後に`--`もDOCTYPEも続かない場合は、`>`までをコメントとして扱う。
+                        // IEの条件付きコメントの`<![endif]>`などがこれにあたる
+                        self.state = State::BogusComment;
+                        self.create_comment();
+                        continue;
+                    }
+
                     if c.is_ascii_alphabetic() {
                         self.reconsume = true;
                         self.state = State::TagName;
@@ -230,12 +560,15 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    // 入力の最後の`<`も文字として出力する。次の呼び出しで入力の終わりになる
                     if self.is_eof() {
-                        return Some(HtmlToken::Eof);
+                        return Some(HtmlToken::Char('<'));
                     }
 
+                    // タグにならない`<`は文字として扱い、続く文字はData状態で読み直す
                     self.reconsume = true;
                     self.state = State::Data;
+                    return Some(HtmlToken::Char('<'));
                 }
 
                 State::EndTagOpen => {
@@ -251,7 +584,7 @@ impl Iterator for HtmlTokenizer {
                     }
                 }
                 State::TagName => {
-                    if c == ' ' {
+                    if is_whitespace(c) {
                         self.state = State::BeforeAttributeName;
                         continue;
                     }
@@ -278,6 +611,11 @@ impl Iterator for HtmlTokenizer {
                     self.append_tag_name(c);
                 }
                 State::BeforeAttributeName => {
+                    if is_whitespace(c) {
+                        // 空白文字は無視する
+                        continue;
+                    }
+
                     if c == '/' || c == '>' || self.is_eof() {
                         self.reconsume = true;
                         self.state = State::AfterAttributeName;
@@ -289,7 +627,7 @@ impl Iterator for HtmlTokenizer {
                     self.start_new_attribute();
                 }
                 State::AttributeName => {
-                    if c == ' ' || c =='/' || c == '>' ||self.is_eof() {
+                    if is_whitespace(c) || c =='/' || c == '>' ||self.is_eof() {
                         self.reconsume = true;
                         self.state = State::AfterAttributeName;
                         continue;
@@ -308,7 +646,7 @@ impl Iterator for HtmlTokenizer {
                     self.append_attribute(c, /*is_name*/ true);
                 }
                 State::AfterAttributeName => {
-                    if c == ' ' {
+                    if is_whitespace(c) {
                         // 空白文字は無視する
                         continue;
                     }
@@ -337,17 +675,19 @@ impl Iterator for HtmlTokenizer {
                     self.start_new_attribute();
                 }
                 State::BeforeAttributeValue => {
-                    if c == ' ' {
+                    if is_whitespace(c) {
                         // 空白文字は無視する
                         continue;
                     }
 
                     if c == '"' {
+                        self.set_attribute_quote(c);
                         self.state = State::AttributeValueDoubleQuoted;
                         continue;
                     }
 
                     if c == '\'' {
+                        self.set_attribute_quote(c);
                         self.state = State::AttributeValueSingleQuoted;
                         continue;
                     }
@@ -365,7 +705,7 @@ impl Iterator for HtmlTokenizer {
                         return Some(HtmlToken::Eof);
                     }
 
-                    self.append_attribute(c, /*is_name*/ false);
+                    self.append_attribute_value(c);
                 }
                 State::AttributeValueSingleQuoted => {
                     if c == '\'' {
@@ -377,10 +717,10 @@ impl Iterator for HtmlTokenizer {
                         return Some(HtmlToken::Eof);
                     }
 
-                    self.append_attribute(c, /*is_name*/ false);
+                    self.append_attribute_value(c);
                 }
                 State::AttributeValueUnquoted => {
-                    if c == ' ' {
+                    if is_whitespace(c) {
                         self.state = State::BeforeAttributeName;
                         continue;
                     }
@@ -394,10 +734,10 @@ impl Iterator for HtmlTokenizer {
                         return Some(HtmlToken::Eof);
                     }
 
-                    self.append_attribute(c, /*is_name*/ false);
+                    self.append_attribute_value(c);
                 }
                 State::AfterAttributeValueQuoted => {
-                    if c == ' ' {
+                    if is_whitespace(c) {
                         self.state = State::BeforeAttributeName;
                         continue;
                     }
@@ -431,6 +771,135 @@ impl Iterator for HtmlTokenizer {
                         return Some(HtmlToken::Eof);
                     }
                 }
+                State::BeforeDoctypeName => {
+                    if c == ' ' || c == '\n' || c == '\t' {
+                        // 空白文字は無視する
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::DoctypeName;
+                }
+                State::DoctypeName => {
+                    if c == ' ' || c == '\n' || c == '\t' {
+                        self.state = State::AfterDoctypeName;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.append_doctype_name(c.to_ascii_lowercase());
+                }
+                State::AfterDoctypeName => {
+                    // PUBLICやSYSTEMなどの識別子はサポートしていないため、`>`まで読み飛ばす
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                }
+                State::BogusComment => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if self.is_eof() {
+                        continue;
+                    }
+
+                    self.append_comment(c);
+                }
+                State::CommentStart => {
+                    if c == '-' {
+                        self.state = State::CommentStartDash;
+                        continue;
+                    }
+
+                    // `<!-->`は空のコメント
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::CommentStartDash => {
+                    if c == '-' {
+                        self.state = State::CommentEnd;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if self.is_eof() {
+                        continue;
+                    }
+
+                    self.append_comment('-');
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::Comment => {
+                    if c == '-' {
+                        self.state = State::CommentEndDash;
+                        continue;
+                    }
+
+                    if self.is_eof() {
+                        continue;
+                    }
+
+                    // コメントの中身はマークアップとして解釈しない。IEの条件付きコメント
+                    // `<!--[if IE]>...<![endif]-->`も、中身を含めて1つのコメントになる
+                    self.append_comment(c);
+                }
+                State::CommentEndDash => {
+                    if c == '-' {
+                        self.state = State::CommentEnd;
+                        continue;
+                    }
+
+                    if self.is_eof() {
+                        continue;
+                    }
+
+                    self.append_comment('-');
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::CommentEnd => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    // `--->`のように余分な`-`は中身に含める
+                    if c == '-' {
+                        self.append_comment('-');
+                        continue;
+                    }
+
+                    if self.is_eof() {
+                        continue;
+                    }
+
+                    self.append_comment('-');
+                    self.append_comment('-');
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
                 State::ScriptData => {
                     if c == '<' {
                         self.state = State::ScriptDataLessThanSign;
@@ -510,6 +979,16 @@ impl Iterator for HtmlTokenizer {
     }
 }
 
+/// `html`を最後までトークン化して返す。パーサを通さずにトークン列を調べるために使う。
+/// 途中でEofが現れてもそこで止め、末尾には必ずEofを1つ置く
+pub fn tokenize(html: String) -> Vec<HtmlToken> {
+    let mut tokens: Vec<HtmlToken> = HtmlTokenizer::new(html)
+        .take_while(|t| *t != HtmlToken::Eof)
+        .collect();
+    tokens.push(HtmlToken::Eof);
+    tokens
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -622,4 +1101,241 @@ mod tests {
             assert_eq!(Some(e), tokenizer.next());
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_doctype() {
+        let html = "<!DOCTYPE html><html>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let expected = [
+            HtmlToken::Doctype {
+                name: "html".to_string(),
+            },
+            HtmlToken::StartTag {
+                tag: "html".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            },
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+    }
+
+    #[test]
+    fn test_predefined_character_references() {
+        let html = "&lt;&gt;&amp;&quot;&apos;".to_string();
+        let tokenizer = HtmlTokenizer::new(html);
+        let expected = ['<', '>', '&', '"', '\''];
+        let actual: Vec<HtmlToken> = tokenizer.collect();
+        assert_eq!(
+            expected.iter().map(|c| HtmlToken::Char(*c)).collect::<Vec<_>>(),
+            actual
+        );
+    }
+
+    #[test]
+    fn test_named_character_references() {
+        let html = "a&nbsp;&copy;&reg;&mdash;&hellip;&rarr;".to_string();
+        let tokenizer = HtmlTokenizer::new(html);
+        let expected = [
+            'a', '\u{00A0}', '\u{00A9}', '\u{00AE}', '\u{2014}', '\u{2026}', '\u{2192}',
+        ];
+        let actual: Vec<HtmlToken> = tokenizer.collect();
+        assert_eq!(
+            expected.iter().map(|c| HtmlToken::Char(*c)).collect::<Vec<_>>(),
+            actual
+        );
+    }
+
+    #[test]
+    fn test_unknown_character_reference() {
+        let html = "&foo; &amp".to_string();
+        let tokenizer = HtmlTokenizer::new(html);
+        let expected = "&foo; &amp";
+        let actual: Vec<HtmlToken> = tokenizer.collect();
+        assert_eq!(
+            expected.chars().map(HtmlToken::Char).collect::<Vec<_>>(),
+            actual
+        );
+    }
+
+    #[test]
+    fn test_less_than_sign_without_tag() {
+        // タグ名にならない文字が続く`<`は、そのまま文字として出力する
+        let html = "1 < 2 <3>".to_string();
+        let tokenizer = HtmlTokenizer::new(html);
+        let expected = "1 < 2 <3>";
+        let actual: Vec<HtmlToken> = tokenizer.collect();
+        assert_eq!(
+            expected.chars().map(HtmlToken::Char).collect::<Vec<_>>(),
+            actual
+        );
+
+        let mut expected: Vec<HtmlToken> = "a <".chars().map(HtmlToken::Char).collect();
+        expected.push(HtmlToken::Eof);
+        assert_eq!(expected, tokenize("a <".to_string()));
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("<p class=a>hi</p>".to_string());
+        let attr = Attribute::from_name_and_value("class".to_string(), "a".to_string());
+        assert_eq!(
+            vec![
+                HtmlToken::StartTag {
+                    tag: "p".to_string(),
+                    self_closing: false,
+                    attributes: vec![attr],
+                },
+                HtmlToken::Char('h'),
+                HtmlToken::Char('i'),
+                HtmlToken::EndTag {
+                    tag: "p".to_string(),
+                },
+                HtmlToken::Eof,
+            ],
+            tokens
+        );
+        assert_eq!(vec![HtmlToken::Eof], tokenize("".to_string()));
+    }
+
+    #[test]
+    fn test_token_position() {
+        let html = "<html>\n  <p>é</p>\n</html>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let mut positions = Vec::new();
+        while let Some(token) = tokenizer.next() {
+            let position = tokenizer.token_position();
+            positions.push((token, position.line(), position.column(), position.offset()));
+        }
+
+        let start_tag = |tag: &str| HtmlToken::StartTag {
+            tag: tag.to_string(),
+            self_closing: false,
+            attributes: Vec::new(),
+        };
+        let end_tag = |tag: &str| HtmlToken::EndTag {
+            tag: tag.to_string(),
+        };
+        assert_eq!(
+            vec![
+                (start_tag("html"), 1, 1, 0),
+                (HtmlToken::Char('\n'), 1, 7, 6),
+                (HtmlToken::Char(' '), 2, 1, 7),
+                (HtmlToken::Char(' '), 2, 2, 8),
+                (start_tag("p"), 2, 3, 9),
+                (HtmlToken::Char('é'), 2, 6, 12),
+                (end_tag("p"), 2, 7, 14),
+                (HtmlToken::Char('\n'), 2, 11, 18),
+                (end_tag("html"), 3, 1, 19),
+            ],
+            positions
+        );
+    }
+
+    #[test]
+    fn test_comment() {
+        let html = "<!-- a -- b --><!---->a<!--->".to_string();
+        let tokens: Vec<HtmlToken> = HtmlTokenizer::new(html).collect();
+        assert_eq!(
+            vec![
+                HtmlToken::Comment(" a -- b ".to_string()),
+                HtmlToken::Comment("".to_string()),
+                HtmlToken::Char('a'),
+                HtmlToken::Comment("".to_string()),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_conditional_comment() {
+        let html = "<!--[if IE]><p class=\"ie\">old</p><![endif]--><![if !IE]>x<![endif]>".to_string();
+        let tokens: Vec<HtmlToken> = HtmlTokenizer::new(html).collect();
+        assert_eq!(
+            vec![
+                HtmlToken::Comment("[if IE]><p class=\"ie\">old</p><![endif]".to_string()),
+                HtmlToken::Comment("[if !IE]".to_string()),
+                HtmlToken::Char('x'),
+                HtmlToken::Comment("[endif]".to_string()),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_eof_in_comment_and_tag() {
+        let tokens: Vec<HtmlToken> = HtmlTokenizer::new("a<!-- b".to_string()).collect();
+        assert_eq!(
+            vec![HtmlToken::Char('a'), HtmlToken::Comment(" b".to_string())],
+            tokens
+        );
+
+        // 閉じられていないタグは捨てる
+        let tokens: Vec<HtmlToken> = HtmlTokenizer::new("a<p clas".to_string()).collect();
+        assert_eq!(vec![HtmlToken::Char('a'), HtmlToken::Eof], tokens);
+    }
+
+    #[test]
+    fn test_length_limits() {
+        let mut html = String::from("<p title=\"");
+        html.push_str(&"a".repeat(100));
+        html.push_str("\" class=b>x</abcdef>");
+        let mut tokenizer = HtmlTokenizer::new(html);
+        tokenizer.set_max_name_length(4);
+        tokenizer.set_max_attribute_value_length(10);
+        let tokens: Vec<HtmlToken> = tokenizer.by_ref().collect();
+
+        let title = "a".repeat(10);
+        assert_eq!(
+            vec![
+                HtmlToken::StartTag {
+                    tag: "p".to_string(),
+                    self_closing: false,
+                    attributes: vec![
+                        Attribute::from_name_and_value("titl".to_string(), title),
+                        Attribute::from_name_and_value("clas".to_string(), "b".to_string()),
+                    ],
+                },
+                HtmlToken::Char('x'),
+                HtmlToken::EndTag {
+                    tag: "abcd".to_string(),
+                },
+            ],
+            tokens
+        );
+
+        let descriptions: Vec<String> = tokenizer
+            .parse_errors()
+            .iter()
+            .map(|e| e.description())
+            .collect();
+        assert_eq!(
+            vec![
+                "attribute name exceeds 4 bytes and is truncated",
+                "attribute value exceeds 10 bytes and is truncated",
+                "attribute name exceeds 4 bytes and is truncated",
+                "tag name exceeds 4 bytes and is truncated",
+            ],
+            descriptions
+        );
+    }
+
+    #[test]
+    fn test_attribute_value_normalization() {
+        let html = "<a title=\"a\r\nb\t&amp;\rc\"\n\thref=x&lt;y&z>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        assert_eq!(
+            Some(HtmlToken::StartTag {
+                tag: "a".to_string(),
+                self_closing: false,
+                attributes: vec![
+                    Attribute::from_name_and_value("title".to_string(), "a\nb\t&\nc".to_string()),
+                    Attribute::from_name_and_value("href".to_string(), "x<y&z".to_string()),
+                ],
+            }),
+            tokenizer.next()
+        );
+        assert_eq!(None, tokenizer.next());
+    }
+}
@@ -0,0 +1,114 @@
+//! トークナイザのテストを表形式でまとめて書くための仕組み。
+//! 各ケースは入力のHTMLと、期待するトークン列を短い文字列で書いたものの組で表す。
+//!
+//! - `StartTag(p class="a")`、自己終了タグは`StartTag(br/)`
+//! - `EndTag(p)`
+//! - 連続する文字トークンは1つにまとめて`Text("ab")`
+//! - `Comment(" x ")`、`Doctype(html)`、`Eof`
+
+use crate::renderer::html::token::HtmlToken;
+use crate::renderer::html::token::HtmlTokenizer;
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// トークン列を、連続する文字をまとめた短い文字列の列に変換する
+pub fn format_tokens(tokens: &[HtmlToken]) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut text = String::new();
+    for token in tokens {
+        if let HtmlToken::Char(c) = token {
+            text.push(*c);
+            continue;
+        }
+        if !text.is_empty() {
+            result.push(format!("Text({:?})", text));
+            text.clear();
+        }
+        result.push(format_token(token));
+    }
+    if !text.is_empty() {
+        result.push(format!("Text({:?})", text));
+    }
+    result
+}
+
+fn format_token(token: &HtmlToken) -> String {
+    match token {
+        HtmlToken::StartTag {
+            tag,
+            self_closing,
+            attributes,
+        } => {
+            let mut s = tag.clone();
+            for attr in attributes {
+                s.push_str(&format!(" {}={:?}", attr.name(), attr.value()));
+            }
+            if *self_closing {
+                s.push('/');
+            }
+            format!("StartTag({})", s)
+        }
+        HtmlToken::EndTag { tag } => format!("EndTag({})", tag),
+        HtmlToken::Doctype { name } => format!("Doctype({})", name),
+        HtmlToken::Char(c) => format!("Text({:?})", c.to_string()),
+        HtmlToken::Comment(data) => format!("Comment({:?})", data),
+        HtmlToken::Eof => "Eof".to_string(),
+    }
+}
+
+/// 全てのケースを実行し、期待と異なったケースを全てまとめて報告する
+pub fn run_cases(cases: &[(&str, &[&str])]) {
+    let mut failures = Vec::new();
+    for (i, (input, expected)) in cases.iter().enumerate() {
+        let tokens: Vec<HtmlToken> = HtmlTokenizer::new(input.to_string()).collect();
+        let actual = format_tokens(&tokens);
+        if actual != *expected {
+            failures.push(format!(
+                "case {} {:?}\n  expected: {:?}\n    actual: {:?}",
+                i, input, expected, actual
+            ));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} tokenizer cases failed:\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_tokenizer_cases() {
+    run_cases(&[
+        // タグ
+        ("<p>a</p>", &["StartTag(p)", "Text(\"a\")", "EndTag(p)"]),
+        ("<P>A</P>", &["StartTag(p)", "Text(\"A\")", "EndTag(p)"]),
+        ("<br/>", &["StartTag(br/)"]),
+        ("<br />", &["StartTag(br/)"]),
+        ("a < b", &["Text(\"a < b\")"]),
+        ("a <", &["Text(\"a <\")"]),
+        ("<p", &["Eof"]),
+        // 属性
+        (
+            "<a href=\"x\" id='y' class=z>",
+            &["StartTag(a href=\"x\" id=\"y\" class=\"z\")"],
+        ),
+        ("<input disabled>", &["StartTag(input disabled=\"\")"]),
+        ("<p ID=A>", &["StartTag(p id=\"A\")"]),
+        ("<a title=\"a>b\">", &["StartTag(a title=\"a>b\")"]),
+        // 文字参照
+        ("&amp;&lt;&gt;", &["Text(\"&<>\")"]),
+        ("&copy; &unknown;", &["Text(\"© &unknown;\")"]),
+        // コメントとDOCTYPE
+        ("<!-- x -->", &["Comment(\" x \")"]),
+        ("<!---->a", &["Comment(\"\")", "Text(\"a\")"]),
+        ("<!-- a -- b -->", &["Comment(\" a -- b \")"]),
+        ("<![endif]>", &["Comment(\"[endif]\")"]),
+        ("<!-- x", &["Comment(\" x\")"]),
+        ("<!DOCTYPE html>", &["Doctype(html)"]),
+        ("<!doctype HTML>\n", &["Doctype(html)", "Text(\"\\n\")"]),
+    ]);
+}
@@ -1,2 +1,4 @@
 pub mod dom;
+pub mod form;
 pub mod html;
+pub mod page;
\ No newline at end of file
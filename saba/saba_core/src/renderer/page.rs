@@ -0,0 +1,177 @@
+use crate::data_url::DataUrl;
+use crate::encoding::decode;
+use crate::encoding::Encoding;
+use crate::error::Error;
+use crate::http::follow_redirects;
+use crate::http::HttpResponse;
+use crate::renderer::dom::node::Window;
+use crate::renderer::html::parser::HtmlParser;
+use crate::renderer::html::token::HtmlTokenizer;
+use crate::url::Url;
+use alloc::format;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+/// `url`の文書を`fetch`で取得し(リダイレクトも辿る)、HTMLとして解析したWindowを返す。
+/// 基準URLにはリダイレクト後の最終的なURLを設定する。Content-Typeヘッダがない場合は、
+/// ボディの先頭がHTMLのタグで始まるかどうかで判定する。
+/// about:blankの場合は`fetch`を呼ばずに空の文書を返し、data:のURLの場合は埋め込まれたデータを使う
+pub fn fetch_document<F>(url: &Url, fetch: F) -> Result<Rc<RefCell<Window>>, Error>
+where
+    F: FnMut(&Url) -> Result<HttpResponse, Error>,
+{
+    if url.scheme() == "about" {
+        if url.is_about_blank() {
+            return Ok(Rc::new(RefCell::new(Window::new())));
+        }
+        return Err(Error::UnexpectedInput(format!(
+            "unsupported about: URL: about:{}",
+            url.path()
+        )));
+    }
+
+    if url.scheme() == "data" {
+        let data = DataUrl::parse(url)?;
+        if !is_html_mime_type(&data.mime_type()) {
+            return Err(Error::UnexpectedInput(format!(
+                "unsupported content type: {}",
+                data.mime_type()
+            )));
+        }
+        let html = decode(&data.body(), Encoding::from_content_type(&data.mime_type()));
+        return Ok(HtmlParser::new(HtmlTokenizer::new(html)).construction_tree());
+    }
+
+    let res = follow_redirects(url.clone(), fetch)?;
+    let response = res.response();
+
+    let content_type = response.content_type();
+    if !is_html_mime_type(&content_type) {
+        return Err(Error::UnexpectedInput(format!(
+            "unsupported content type: {}",
+            content_type
+        )));
+    }
+
+    let window = HtmlParser::new(HtmlTokenizer::new(response.body())).construction_tree();
+    // <base>要素が基準URLを決めていなければ、文書のURLを使う
+    if window.borrow().base_url().is_none() {
+        window.borrow_mut().set_base_url(res.url());
+    }
+    Ok(window)
+}
+
+/// Content-Typeヘッダの値が、本書のブラウザが解析できるHTMLを表すか判定する
+fn is_html_mime_type(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    essence.eq_ignore_ascii_case("text/html")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use crate::renderer::dom::node::ElementKind;
+
+    fn fake_fetch(url: &Url) -> Result<HttpResponse, Error> {
+        let raw = match url.path().as_str() {
+            "old" => "HTTP/1.1 301 Moved Permanently\nLocation: /dir/index.html\n\n".to_string(),
+            "dir/index.html" => "HTTP/1.1 200 OK\nContent-Type: text/html; charset=utf-8\n\n<html><head></head><body><p>hello</p></body></html>".to_string(),
+            "image.png" => "HTTP/1.1 200 OK\nContent-Type: image/png\n\nxx".to_string(),
+            "no-type" => "HTTP/1.1 200 OK\nDate: xx xx xx\n\n<!DOCTYPE html><html><head></head><body><p>sniffed</p></body></html>".to_string(),
+            "no-type.txt" => "HTTP/1.1 200 OK\nDate: xx xx xx\n\nplain <p>text</p>".to_string(),
+            _ => "HTTP/1.1 404 NotFound\nDate: xx xx xx\n\n".to_string(),
+        };
+        HttpResponse::new(raw)
+    }
+
+    fn url(s: &str) -> Url {
+        Url::new(s.to_string()).parse().expect("failed to parse url")
+    }
+
+    #[test]
+    fn test_fetch_document() {
+        let window = fetch_document(&url("http://example.com/old"), fake_fetch)
+            .expect("failed to fetch a document");
+
+        let html = window
+            .borrow()
+            .document()
+            .borrow()
+            .first_child()
+            .expect("failed to get html");
+        assert_eq!(Some(ElementKind::Html), html.borrow().element_kind());
+        let body = html.borrow().last_child().upgrade().expect("failed to get body");
+        let p = body.borrow().first_child().expect("failed to get p");
+        assert_eq!(Some(ElementKind::P), p.borrow().element_kind());
+
+        // 基準URLはリダイレクト後のURLになる
+        let base_url = window.borrow().base_url().expect("failed to get base url");
+        assert_eq!("dir/index.html", base_url.path());
+    }
+
+    #[test]
+    fn test_fetch_non_html_document() {
+        match fetch_document(&url("http://example.com/image.png"), fake_fetch) {
+            Err(Error::UnexpectedInput(_)) => {}
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_fetch_about_blank() {
+        let mut calls = 0;
+        let window = fetch_document(&url("about:blank"), |u| {
+            calls += 1;
+            fake_fetch(u)
+        })
+        .expect("failed to fetch a document");
+
+        assert_eq!(0, calls);
+        assert!(window.borrow().document().borrow().first_child().is_none());
+        assert!(fetch_document(&url("about:unknown"), fake_fetch).is_err());
+    }
+
+    #[test]
+    fn test_fetch_data_url() {
+        let window = fetch_document(
+            &url("data:text/html;base64,PGh0bWw+PGhlYWQ+PC9oZWFkPjxib2R5PjxwPmE8L3A+PC9ib2R5PjwvaHRtbD4="),
+            |_| panic!("data: URLs must not be fetched"),
+        )
+        .expect("failed to fetch a document");
+
+        let html = window
+            .borrow()
+            .document()
+            .borrow()
+            .first_child()
+            .expect("failed to get html");
+        let body = html.borrow().last_child().upgrade().expect("failed to get body");
+        let p = body.borrow().first_child().expect("failed to get p");
+        assert_eq!(Some(ElementKind::P), p.borrow().element_kind());
+
+        assert!(fetch_document(&url("data:text/plain,a"), fake_fetch).is_err());
+    }
+
+    #[test]
+    fn test_fetch_document_without_content_type() {
+        let url = Url::new("http://example.com/no-type".to_string())
+            .parse()
+            .expect("failed to parse url");
+        let window = fetch_document(&url, fake_fetch).expect("failed to fetch document");
+        let document = window.borrow().document();
+        let html = document.borrow().first_child().expect("failed to get html");
+        assert_eq!(Some(ElementKind::Html), html.borrow().element_kind());
+
+        // HTMLのタグで始まらないボディはテキストとみなし、HTMLとしては解析しない
+        let url = Url::new("http://example.com/no-type.txt".to_string())
+            .parse()
+            .expect("failed to parse url");
+        match fetch_document(&url, fake_fetch) {
+            Err(Error::UnexpectedInput(message)) => {
+                assert_eq!("unsupported content type: text/plain", message)
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
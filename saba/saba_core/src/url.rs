@@ -1,3 +1,4 @@
+use alloc::format;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec::Vec;
@@ -22,6 +23,25 @@ impl Url {
         }
     }
 
+    /// URLの`:`より前の部分を小文字で返す(例: "http"、"about")
+    pub fn scheme(&self) -> String {
+        match self.url.split_once(':') {
+            Some((scheme, _)) => scheme.to_ascii_lowercase(),
+            None => "".to_string(),
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#matches-about:blank
+    pub fn is_about_blank(&self) -> bool {
+        self.scheme() == "about" && self.path == "blank" && self.searchpart.is_empty()
+    }
+
+    /// https://url.spec.whatwg.org/#dom-url-href
+    /// 解決済みのURLの場合は、URL全体の文字列を返す
+    pub fn href(&self) -> String {
+        self.url.clone()
+    }
+
     pub fn host(&self) -> String {
         self.host.clone()
     }
@@ -108,6 +128,45 @@ impl Url {
     }
 
     pub fn parse(&mut self) -> Result<Self, String> {
+        // https://www.rfc-editor.org/rfc/rfc6694
+        // about:のURLはホストを持たず、`:`以降をパスとして扱う
+        if self.scheme() == "about" {
+            let (path, searchpart) = match self.url[6..].split_once('?') {
+                Some((p, s)) => (p, s),
+                None => (&self.url[6..], ""),
+            };
+            self.host = "".to_string();
+            self.port = "".to_string();
+            self.path = path.to_string();
+            self.searchpart = searchpart.to_string();
+            return Ok(self.clone());
+        }
+
+        // https://fetch.spec.whatwg.org/#data-urls
+        // data:のURLの`:`以降は全てデータとして扱うため、`?`でも区切らない
+        if self.scheme() == "data" {
+            self.host = "".to_string();
+            self.port = "".to_string();
+            self.path = self.url[5..].to_string();
+            self.searchpart = "".to_string();
+            return Ok(self.clone());
+        }
+
+        // https://url.spec.whatwg.org/#file-state
+        // ホストを持たないfile:///path形式のみをサポートする
+        if self.scheme() == "file" {
+            let rest = self.url[5..].trim_start_matches('/');
+            let (path, searchpart) = match rest.split_once('?') {
+                Some((p, s)) => (p, s),
+                None => (rest, ""),
+            };
+            self.host = "".to_string();
+            self.port = "".to_string();
+            self.path = path.to_string();
+            self.searchpart = searchpart.to_string();
+            return Ok(self.clone());
+        }
+
         if !self.is_http() {
             return  Err("Only HTTP scheme is supported.".to_string());
         }
@@ -119,6 +178,118 @@ impl Url {
 
         Ok(self.clone())
     }
+
+    fn serialize(&self, path: &str, searchpart: &str) -> String {
+        let mut url = if self.scheme() == "file" {
+            String::from("file://")
+        } else {
+            let mut url = String::from("http://");
+            url.push_str(&self.host);
+            if self.port != "80" {
+                url.push(':');
+                url.push_str(&self.port);
+            }
+            url
+        };
+        url.push('/');
+        url.push_str(path);
+        if !searchpart.is_empty() {
+            url.push('?');
+            url.push_str(searchpart);
+        }
+        url
+    }
+
+    /// https://url.spec.whatwg.org/#concept-basic-url-parser
+    /// 自身を基準URLとして、`input`で与えられた相対URLを絶対URLに解決する
+    pub fn join(&self, input: &str) -> Result<Self, String> {
+        // フラグメントはサポートしていないため取り除く
+        let input = match input.split_once('#') {
+            Some((i, _fragment)) => i,
+            None => input,
+        };
+
+        if let Some(index) = input.find("://") {
+            if input[..index].chars().all(|c| c.is_ascii_alphabetic()) {
+                return Url::new(input.to_string()).parse();
+            }
+        }
+
+        if let Some((scheme, _)) = input.split_once(':') {
+            if ["about", "data", "file"]
+                .iter()
+                .any(|s| scheme.eq_ignore_ascii_case(s))
+            {
+                return Url::new(input.to_string()).parse();
+            }
+        }
+
+        // 階層を持たない基準URLに対して相対URLは解決できない
+        let scheme = self.scheme();
+        if scheme != "http" && scheme != "file" {
+            return Err(format!("cannot resolve {} against {}", input, self.url));
+        }
+
+        if input.starts_with("//") {
+            if scheme == "file" {
+                return Url::new(format!("file:{}", input)).parse();
+            }
+            return Url::new(format!("http:{}", input)).parse();
+        }
+
+        if input.is_empty() {
+            return Url::new(self.serialize(&self.path, &self.searchpart)).parse();
+        }
+
+        if let Some(searchpart) = input.strip_prefix('?') {
+            return Url::new(self.serialize(&self.path, searchpart)).parse();
+        }
+
+        let (path, searchpart) = match input.split_once('?') {
+            Some((p, s)) => (p, s),
+            None => (input, ""),
+        };
+
+        let merged = match path.strip_prefix('/') {
+            Some(p) => p.to_string(),
+            None => {
+                // 基準URLのパスの最後の`/`までをディレクトリとして扱う
+                let directory = match self.path.rfind('/') {
+                    Some(index) => &self.path[..index + 1],
+                    None => "",
+                };
+                format!("{}{}", directory, path)
+            }
+        };
+
+        Url::new(self.serialize(&remove_dot_segments(&merged), searchpart)).parse()
+    }
+}
+
+/// https://datatracker.ietf.org/doc/html/rfc3986#section-5.2.4
+fn remove_dot_segments(path: &str) -> String {
+    let segments: Vec<&str> = path.split('/').collect();
+    let mut output: Vec<&str> = Vec::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        let is_last = i == segments.len() - 1;
+        match *segment {
+            "." => {}
+            ".." => {
+                output.pop();
+            }
+            s => {
+                output.push(s);
+                continue;
+            }
+        }
+        // `.`や`..`で終わるパスはディレクトリを指すので、末尾の`/`を残す
+        if is_last {
+            output.push("");
+        }
+    }
+
+    output.join("/")
 }
 
 #[cfg(test)]
@@ -203,4 +374,120 @@ mod tests {
         let expected = Err("Only HTTP scheme is supported.".to_string());
         assert_eq!(expected, Url::new(url).parse());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_join_absolute() {
+        let base = Url::new("http://example.com/dir/index.html".to_string())
+            .parse()
+            .expect("failed to parse base url");
+        let url = base
+            .join("http://other.com:8000/a.html")
+            .expect("failed to join url");
+        assert_eq!("other.com", url.host());
+        assert_eq!("8000", url.port());
+        assert_eq!("a.html", url.path());
+    }
+
+    #[test]
+    fn test_join_scheme_relative() {
+        let base = Url::new("http://example.com/dir/index.html".to_string())
+            .parse()
+            .expect("failed to parse base url");
+        let url = base.join("//other.com/a.html").expect("failed to join url");
+        assert_eq!("other.com", url.host());
+        assert_eq!("80", url.port());
+        assert_eq!("a.html", url.path());
+    }
+
+    #[test]
+    fn test_join_root_relative() {
+        let base = Url::new("http://example.com:8888/dir/index.html?a=1".to_string())
+            .parse()
+            .expect("failed to parse base url");
+        let url = base.join("/top.html?b=2").expect("failed to join url");
+        assert_eq!("example.com", url.host());
+        assert_eq!("8888", url.port());
+        assert_eq!("top.html", url.path());
+        assert_eq!("b=2", url.searchpart());
+    }
+
+    #[test]
+    fn test_join_relative() {
+        let base = Url::new("http://example.com/dir/sub/index.html?a=1".to_string())
+            .parse()
+            .expect("failed to parse base url");
+        let url = base.join("page.html").expect("failed to join url");
+        assert_eq!("dir/sub/page.html", url.path());
+        assert_eq!("", url.searchpart());
+
+        let url = base.join("../page.html#top").expect("failed to join url");
+        assert_eq!("dir/page.html", url.path());
+
+        let url = base.join("./").expect("failed to join url");
+        assert_eq!("dir/sub/", url.path());
+    }
+
+    #[test]
+    fn test_join_searchpart_only() {
+        let base = Url::new("http://example.com/index.html?a=1".to_string())
+            .parse()
+            .expect("failed to parse base url");
+        let url = base.join("?b=2").expect("failed to join url");
+        assert_eq!("index.html", url.path());
+        assert_eq!("b=2", url.searchpart());
+    }
+
+    #[test]
+    fn test_about_blank() {
+        let url = Url::new("about:blank".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!("about", url.scheme());
+        assert_eq!("", url.host());
+        assert_eq!("blank", url.path());
+        assert!(url.is_about_blank());
+
+        let url = Url::new("about:config".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert!(!url.is_about_blank());
+
+        let url = Url::new("http://example.com/blank".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!("http", url.scheme());
+        assert!(!url.is_about_blank());
+    }
+
+    #[test]
+    fn test_join_with_about_blank() {
+        let base = Url::new("about:blank".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert!(base.join("page.html").is_err());
+        let url = base
+            .join("http://example.com/page.html")
+            .expect("failed to join url");
+        assert_eq!("example.com", url.host());
+
+        let base = Url::new("http://example.com/dir/".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert!(base.join("about:blank").expect("failed to join url").is_about_blank());
+    }
+
+    #[test]
+    fn test_file_url() {
+        let url = Url::new("file:///home/user/index.html?a=1".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!("file", url.scheme());
+        assert_eq!("", url.host());
+        assert_eq!("home/user/index.html", url.path());
+        assert_eq!("a=1", url.searchpart());
+
+        let joined = url.join("../other.html").expect("failed to join url");
+        assert_eq!("file", joined.scheme());
+        assert_eq!("home/other.html", joined.path());
+    }
+}